@@ -1,40 +1,324 @@
 use std::{io::Write, path::PathBuf, process::ExitCode};
 
+use sshscan_core::{
+    targets::{Address, Ports, Target},
+    NmapError,
+};
+
+mod serve;
+
 const HELP: &str = "\
 Usage: sshscan [OPTIONS] <COMMAND>
 
 Commands:
   generate <input_file>              Generate a report from existing nmap XML scan data
-  scan <cidr> [port] [aggressive]    Run nmap and generate a report
+  scan <target>... [ports] [aggressive]
+                                     Run nmap and generate a report
+  check <input_file>                 Like generate, but exit with status 2 if any policy violation
+                                     (broken algorithm, SSHv1, Terrapin, changed host key) is found
+  trend [input_file...]              Compare scans (nmap XML, JSON or all scans in --db) over time
+  history list                       List scans stored in the --db database
+  history export <id>                Write a report for a stored scan
+  serve <target>... [ports] [aggressive]
+                                     Scan periodically and serve the latest HTML report, JSON API
+                                     (/api/scan) and Prometheus metrics (/metrics) over HTTP
 
 Options:
   -o, --output <FILE>    Output file to write (use '-' for stdout) [default: stdout]
+  -f, --format <FORMAT>  Report format [default: html] [possible values: html, markdown, text,
+                         sarif, junit, metrics, json, known-hosts, sshfp]
   -i, --include-down     Include hosts that are down in the report [default: false]
+      --hash-known-hosts Hash host names in '-f known-hosts' output, like ssh-keygen -H
+  -c, --client-profile <FILE>
+                         Load additional client profiles for negotiation prediction (repeatable)
+  -t, --template <FILE>  Render the report with a custom template instead of the default layout
+      --dump-template    Print a starter template to build custom templates from and exit
+      --title <TEXT>     Report title
+      --subtitle <TEXT>  Text shown below the report title
+      --css <FILE>       Additional CSS applied after the built-in style
+      --logo <FILE>      Logo image (PNG, JPEG, GIF or SVG) or data: URI shown next to the title
+      --footer <TEXT>    Footer text, e.g. classification or legal notice
+      --timezone <TZ>    Timezone for timestamps: local, utc or an offset like +02:00 [default: local]
+      --time-format <FMT>
+                         strftime format for timestamps [default: %Y-%m-%dT%H.%M%z]
+      --previous <FILE>  Flag host keys that changed since this earlier scan (nmap XML or JSON)
+      --known-hosts <FILE>
+                         Flag host keys that differ from, or are revoked in, this OpenSSH known_hosts
+                         file
+      --db <FILE>        Record every scan in this SQLite database (requires the sqlite feature)
+      --site <GROUPING>  Write a multi-page site into the output directory, with one page
+                         per host or per /24 subnet [possible values: host, subnet]
   -h, --help             Print help
   -V, --version          Print version
+
+Exit status:
+  0  Success
+  1  Error, e.g. invalid arguments, unreadable input or nmap failure
+  2  'check' found policy violations
 ";
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Exit status of `check` when policy violations are found, distinct from errors (1)
+const VIOLATION: u8 = 2;
+
 enum OutputType {
     Stdout,
     File(PathBuf),
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Html,
+    Markdown,
+    Text,
+    Sarif,
+    Junit,
+    Metrics,
+    Json,
+    KnownHosts,
+    Sshfp,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(Self::Html),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "text" => Ok(Self::Text),
+            "sarif" => Ok(Self::Sarif),
+            "junit" => Ok(Self::Junit),
+            "metrics" | "prometheus" => Ok(Self::Metrics),
+            "json" => Ok(Self::Json),
+            "known-hosts" | "known_hosts" => Ok(Self::KnownHosts),
+            "sshfp" => Ok(Self::Sshfp),
+            _ => Err(format!(
+                "unknown format '{s}', expected one of: html, markdown, text, sarif, junit, metrics, json, known-hosts, sshfp"
+            )),
+        }
+    }
+}
+
 struct SshScanConfig {
     output_file: OutputType,
+    format: OutputFormat,
     include_down: bool,
+    hash_known_hosts: bool,
+    client_profiles: Vec<sshscan_core::negotiate::ClientProfile>,
+    template: Option<sshscan_core::template::Template>,
+    report_options: sshscan_core::html::ReportOptions,
+    site: Option<sshscan_core::html::SiteGrouping>,
+    db: Option<PathBuf>,
+    previous: Option<Vec<sshscan_core::Host>>,
+    known_hosts: Vec<sshscan_core::known_hosts::Entry>,
 }
 
 enum Command {
     Generate {
         input_file: PathBuf,
     },
-    Scan {
-        cidr: String,
-        port: u16,
-        aggressive: bool,
+    Check {
+        input_file: PathBuf,
+        baseline: Option<PathBuf>,
+        save_baseline: Option<PathBuf>,
+    },
+    Scan(ScanSpec),
+    History(HistoryAction),
+    Trend {
+        inputs: Vec<PathBuf>,
     },
+    Serve {
+        spec: ScanSpec,
+        listen: String,
+        interval: u64,
+    },
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+enum HistoryAction {
+    List,
+    Export { id: i64 },
+}
+
+fn arg_error(e: pico_args::Error) -> ExitCode {
+    eprintln!("Error: {e}");
+    ExitCode::FAILURE
+}
+
+/// What `scan` and `serve` run nmap against
+struct ScanSpec {
+    targets: Vec<Target>,
+    exclude: Vec<Address>,
+    /// Ports for targets without their own
+    ports: Ports,
+    /// nmap timing template, 0 (paranoid) to 5 (insane)
+    timing: u8,
+    /// Resolve host names to IPv6 addresses
+    ipv6: bool,
+    /// Skip host discovery (`-Pn`)
+    no_ping: bool,
+    source_port: Option<u16>,
+    max_rate: Option<u32>,
+    script_args: Option<String>,
+    /// Passed to nmap as is, after the built-in arguments
+    nmap_args: Vec<String>,
+}
+
+impl ScanSpec {
+    /// Whether `address` has to be scanned with `nmap -6`
+    fn is_ipv6(&self, address: &Address) -> bool {
+        address.is_ipv6().unwrap_or(self.ipv6)
+    }
+}
+
+const SCAN_SPEC_HELP: &str = "\
+  <target>...            Addresses, CIDR networks (10.0.0.0/24), ranges (10.0.0.1-50) or host names,
+                         each optionally followed by :ports (e.g. host:22,2222)
+  [ports]                Ports for targets without their own, e.g. 22,2222 or 2200-2299 [default: 22]
+  [aggressive]           true for --timing T5, false for T1 [default: true]
+  --targets-file <FILE>  Read additional targets from FILE, one per line with optional :ports
+                         ('#' starts a comment)
+  --exclude <TARGETS>    Comma separated addresses, networks, ranges or host names not to scan (repeatable)
+  --exclude-file <FILE>  Read exclusions from FILE, one per line
+  --known-hosts-targets  Also scan the hosts listed in --known-hosts
+  -6, --ipv6             Resolve host names to IPv6 addresses. IPv6 addresses and networks (at most a /116)
+                         are always scanned over IPv6, written as [addr]:ports when giving ports
+  --timing <T0..T5>      nmap timing template, overrides [aggressive] [default: T5]
+  -Pn, --no-ping         Skip host discovery, for networks where ping probes are blocked
+  --source-port <PORT>   Send probes from this source port
+  --max-rate <N>         Send no more than N packets per second
+  --script-args <ARGS>   Arguments for the nmap scripts, e.g. ssh.user=audit
+  --nmap-arg <ARG>       Extra argument passed to nmap as is (repeatable)";
+
+/// Targets, exclusions, port and timing of `scan` and `serve`, all validated before anything is scanned
+fn scan_spec(pargs: &mut pico_args::Arguments, config: &SshScanConfig, command: &str) -> Result<ScanSpec, ExitCode> {
+    let invalid = |e: sshscan_core::SshScanErr| {
+        eprintln!("Error: {e}");
+        ExitCode::FAILURE
+    };
+    let mut targets = Vec::new();
+    if pargs.contains("--known-hosts-targets") {
+        for name in sshscan_core::known_hosts::targets(&config.known_hosts) {
+            match sshscan_core::targets::parse_address(&name.name) {
+                Ok(address) => targets.push(Target { address, ports: Some(Ports::single(name.port)) }),
+                Err(e) => eprintln!("Warning: skipping known_hosts entry: {e}"),
+            }
+        }
+        if targets.is_empty() {
+            eprintln!("Error: --known-hosts-targets requires a --known-hosts file with plain host names");
+            return Err(ExitCode::FAILURE);
+        }
+    }
+    let targets_files: Vec<PathBuf> = pargs.values_from_str("--targets-file").map_err(arg_error)?;
+    for path in targets_files {
+        targets.extend(load_file(&path, "targets", |data| sshscan_core::targets::parse_file(&data))?);
+    }
+    let mut exclude = Vec::new();
+    let exclude_args: Vec<String> = pargs.values_from_str("--exclude").map_err(arg_error)?;
+    for arg in exclude_args {
+        for spec in arg.split(',').map(str::trim).filter(|spec| !spec.is_empty()) {
+            exclude.push(sshscan_core::targets::parse_exclusion(spec).map_err(invalid)?);
+        }
+    }
+    let exclude_files: Vec<PathBuf> = pargs.values_from_str("--exclude-file").map_err(arg_error)?;
+    for path in exclude_files {
+        exclude.extend(load_file(&path, "exclusions", |data| sshscan_core::targets::parse_exclude_file(&data))?);
+    }
+    let ipv6 = pargs.contains(["-6", "--ipv6"]);
+    let timing: Option<u8> = pargs.opt_value_from_fn("--timing", parse_timing).map_err(arg_error)?;
+    let mut no_ping = pargs.contains("--no-ping");
+    let source_port: Option<u16> = pargs.opt_value_from_str("--source-port").map_err(arg_error)?;
+    let max_rate: Option<u32> = pargs.opt_value_from_str("--max-rate").map_err(arg_error)?;
+    let script_args: Option<String> = pargs.opt_value_from_str("--script-args").map_err(arg_error)?;
+    let nmap_args: Vec<String> = pargs.values_from_str("--nmap-arg").map_err(arg_error)?;
+    // sshscan reads nmap's XML from stdout, other output options would take it away
+    if let Some(arg) = nmap_args.iter().find(|arg| arg.starts_with("-o")) {
+        eprintln!("Error: --nmap-arg {arg} is not supported, sshscan sets nmap's output options itself");
+        return Err(ExitCode::FAILURE);
+    }
+    let (mut ports, mut aggressive) = (Ports::single(22), true);
+    while let Some(arg) = pargs.opt_free_from_str::<String>().map_err(arg_error)? {
+        // pico-args only knows single letter short flags, so nmap's -Pn is picked out here, after
+        // every option has taken its value
+        if arg == "-Pn" {
+            no_ping = true;
+        } else if let Ok(value) = arg.parse() {
+            aggressive = value;
+        } else if arg.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '-') {
+            ports = arg.parse().map_err(invalid)?;
+        } else {
+            targets.push(sshscan_core::targets::parse_target(&arg).map_err(invalid)?);
+        }
+    }
+    if targets.is_empty() {
+        eprintln!("Error: '{command}' requires at least one <target>");
+        return Err(ExitCode::FAILURE);
+    }
+    Ok(ScanSpec {
+        targets,
+        exclude,
+        ports,
+        timing: timing.unwrap_or(if aggressive { 5 } else { 1 }),
+        ipv6,
+        no_ping,
+        source_port,
+        max_rate,
+        script_args,
+        nmap_args,
+    })
+}
+
+/// Parse an nmap timing template: `T0` to `T5`, `0` to `5` or its name, e.g. `polite`
+fn parse_timing(s: &str) -> Result<u8, String> {
+    const NAMES: [&str; 6] = ["paranoid", "sneaky", "polite", "normal", "aggressive", "insane"];
+    let level = s.strip_prefix(['T', 't']).unwrap_or(s);
+    level
+        .parse()
+        .ok()
+        .or_else(|| NAMES.iter().position(|name| name.eq_ignore_ascii_case(s)).and_then(|i| u8::try_from(i).ok()))
+        .filter(|level| *level <= 5)
+        .ok_or_else(|| format!("invalid timing '{s}', expected T0 to T5"))
+}
+
+/// Read and parse a file given on the command line, reporting failures to the user
+fn load_file<T>(
+    path: &std::path::Path,
+    what: &str,
+    parse: impl FnOnce(String) -> Result<T, sshscan_core::SshScanErr>,
+) -> Result<T, ExitCode> {
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| parse(data).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            eprintln!("Error: failed to load {what} from {}: {e}", path.to_string_lossy());
+            ExitCode::FAILURE
+        })
+}
+
+/// Turn `--logo` into a data URI, reading and encoding the file unless it already is one
+fn load_logo(logo: &str) -> Result<String, ExitCode> {
+    use base64::Engine;
+    if logo.starts_with("data:") {
+        return Ok(logo.to_owned());
+    }
+    let path = std::path::Path::new(logo);
+    let mime = match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => {
+            eprintln!("Error: unsupported logo format, expected a .png, .jpg, .gif or .svg file");
+            return Err(ExitCode::FAILURE);
+        }
+    };
+    let data = std::fs::read(path).map_err(|e| {
+        eprintln!("Error: failed to read logo from {logo}: {e}");
+        ExitCode::FAILURE
+    })?;
+    Ok(format!("data:{mime};base64,{}", base64::engine::general_purpose::STANDARD.encode(data)))
 }
 
 /// Parse arguments from the environment.
@@ -52,14 +336,62 @@ fn parse_args() -> Result<(SshScanConfig, Command), ExitCode> {
         println!("sshscan {VERSION}");
         return Err(ExitCode::SUCCESS);
     }
+    if pargs.contains("--dump-template") {
+        print!("{}", sshscan_core::template::DEFAULT);
+        return Err(ExitCode::SUCCESS);
+    }
 
     // Global options — consume before the subcommand name so they work in either position.
     let output_file_raw: Option<String> =
-        pargs.opt_value_from_str(["-o", "--output"]).map_err(|e| {
-            eprintln!("Error: {e}");
-            ExitCode::FAILURE
-        })?;
+        pargs.opt_value_from_str(["-o", "--output"]).map_err(arg_error)?;
+    let format: OutputFormat = pargs
+        .opt_value_from_str(["-f", "--format"])
+        .map_err(arg_error)?
+        .unwrap_or(OutputFormat::Html);
     let include_down = pargs.contains(["-i", "--include-down"]);
+    let hash_known_hosts = pargs.contains("--hash-known-hosts");
+    let profile_files: Vec<PathBuf> =
+        pargs.values_from_str(["-c", "--client-profile"]).map_err(arg_error)?;
+    let template_file: Option<PathBuf> =
+        pargs.opt_value_from_str(["-t", "--template"]).map_err(arg_error)?;
+    let css_file: Option<PathBuf> = pargs.opt_value_from_str("--css").map_err(arg_error)?;
+    let logo: Option<String> = pargs.opt_value_from_str("--logo").map_err(arg_error)?;
+    let report_options = sshscan_core::html::ReportOptions {
+        title: pargs.opt_value_from_str("--title").map_err(arg_error)?,
+        subtitle: pargs.opt_value_from_str("--subtitle").map_err(arg_error)?,
+        extra_css: css_file
+            .map(|css_file| load_file(&css_file, "CSS", Ok))
+            .transpose()?,
+        logo: logo.as_deref().map(load_logo).transpose()?,
+        footer: pargs.opt_value_from_str("--footer").map_err(arg_error)?,
+        timezone: pargs.opt_value_from_str("--timezone").map_err(arg_error)?.unwrap_or_default(),
+        time_format: pargs.opt_value_from_str("--time-format").map_err(arg_error)?,
+    };
+    let site: Option<sshscan_core::html::SiteGrouping> =
+        pargs.opt_value_from_str("--site").map_err(arg_error)?;
+    let db: Option<PathBuf> = pargs.opt_value_from_str("--db").map_err(arg_error)?;
+    let previous_file: Option<PathBuf> = pargs.opt_value_from_str("--previous").map_err(arg_error)?;
+    let previous = previous_file
+        .map(|path| {
+            load_scan_file(&path, false).map(|(_, hosts)| hosts).map_err(|e| {
+                eprintln!("Error: failed to load previous scan from {}: {e}", path.to_string_lossy());
+                ExitCode::FAILURE
+            })
+        })
+        .transpose()?;
+    let known_hosts_file: Option<PathBuf> = pargs.opt_value_from_str("--known-hosts").map_err(arg_error)?;
+    let known_hosts = known_hosts_file
+        .map(|path| load_file(&path, "known_hosts", |data| sshscan_core::known_hosts::parse(&data)))
+        .transpose()?
+        .unwrap_or_default();
+    if cfg!(not(feature = "sqlite")) && db.is_some() {
+        eprintln!("Error: --db requires sshscan to be built with the sqlite feature");
+        return Err(ExitCode::FAILURE);
+    }
+    if let Err(e) = report_options.validate() {
+        eprintln!("Error: {e}");
+        return Err(ExitCode::FAILURE);
+    }
 
     let output_file = match output_file_raw {
         None => OutputType::Stdout,
@@ -67,9 +399,48 @@ fn parse_args() -> Result<(SshScanConfig, Command), ExitCode> {
         Some(s) => OutputType::File(PathBuf::from(s)),
     };
 
+    if format != OutputFormat::Html && (site.is_some() || template_file.is_some()) {
+        eprintln!("Error: --site and --template are only supported with the html format");
+        return Err(ExitCode::FAILURE);
+    }
+    if site.is_some() {
+        if matches!(output_file, OutputType::Stdout) {
+            eprintln!("Error: --site requires an output directory (-o <DIR>)");
+            return Err(ExitCode::FAILURE);
+        }
+        if template_file.is_some() {
+            eprintln!("Error: --site and --template cannot be used together");
+            return Err(ExitCode::FAILURE);
+        }
+    }
+
+    let mut client_profiles = sshscan_core::negotiate::builtin_profiles();
+    for profile_file in profile_files {
+        client_profiles.extend(load_file(&profile_file, "client profiles", |data| {
+            sshscan_core::negotiate::parse_profiles(&data)
+        })?);
+    }
+
+    let template = template_file
+        .map(|template_file| {
+            load_file(&template_file, "template", |data| {
+                sshscan_core::template::Template::parse(&data)
+            })
+        })
+        .transpose()?;
+
     let config = SshScanConfig {
         output_file,
+        format,
         include_down,
+        hash_known_hosts,
+        client_profiles,
+        template,
+        report_options,
+        site,
+        db,
+        previous,
+        known_hosts,
     };
 
     // The next free argument is the subcommand name.
@@ -93,24 +464,105 @@ fn parse_args() -> Result<(SshScanConfig, Command), ExitCode> {
             })?;
             Command::Generate { input_file }
         }
+        "check" => {
+            if pargs.contains(["-h", "--help"]) {
+                println!("Usage: sshscan [OPTIONS] check [--baseline <FILE>] [--save-baseline <FILE>] <input_file>\n");
+                println!("  <input_file>              nmap XML file to read from");
+                println!("  --baseline <FILE>         Only report deviations from this accepted scan (nmap XML or JSON):");
+                println!("                            new findings, new SSH servers and changed host keys");
+                println!("  --save-baseline <FILE>    Save this scan as JSON for later use with --baseline");
+                println!("\nWrites the report like 'generate' and exits with status 2 if any policy violation is found");
+                return Err(ExitCode::SUCCESS);
+            }
+            let baseline: Option<PathBuf> = pargs.opt_value_from_str("--baseline").map_err(arg_error)?;
+            let save_baseline: Option<PathBuf> = pargs.opt_value_from_str("--save-baseline").map_err(arg_error)?;
+            let input_file: PathBuf = pargs.free_from_str().map_err(|_| {
+                eprintln!("Error: 'check' requires <input_file>");
+                ExitCode::FAILURE
+            })?;
+            Command::Check { input_file, baseline, save_baseline }
+        }
         "scan" => {
             if pargs.contains(["-h", "--help"]) {
-                println!("Usage: sshscan [OPTIONS] scan <cidr> [port] [aggressive]\n");
-                println!("  <cidr>          Target range in CIDR notation (e.g. 10.0.0.0/24)");
-                println!("  [port]          Port to scan [default: 22]");
-                println!("  [aggressive]    Use aggressive timing (-T5) [default: true]");
+                println!("Usage: sshscan [OPTIONS] scan [SCAN OPTIONS] <target>... [ports] [aggressive]\n");
+                println!("{SCAN_SPEC_HELP}");
+                return Err(ExitCode::SUCCESS);
+            }
+            Command::Scan(scan_spec(&mut pargs, &config, "scan")?)
+        }
+        "trend" => {
+            if pargs.contains(["-h", "--help"]) {
+                println!("Usage: sshscan [OPTIONS] trend [input_file...]\n");
+                println!("  [input_file...]    nmap XML or sshscan JSON files to compare, in addition to all scans in --db");
+                println!("\nSupports the html and markdown formats");
+                return Err(ExitCode::SUCCESS);
+            }
+            if !matches!(format, OutputFormat::Html | OutputFormat::Markdown) || site.is_some() || config.template.is_some() {
+                eprintln!("Error: 'trend' only supports the html and markdown formats, without --site or --template");
+                return Err(ExitCode::FAILURE);
+            }
+            let mut inputs = Vec::new();
+            while let Some(input) = pargs.opt_free_from_str::<PathBuf>().map_err(arg_error)? {
+                inputs.push(input);
+            }
+            if inputs.is_empty() && config.db.is_none() {
+                eprintln!("Error: 'trend' requires input files or --db");
+                return Err(ExitCode::FAILURE);
+            }
+            Command::Trend { inputs }
+        }
+        "history" => {
+            if pargs.contains(["-h", "--help"]) {
+                println!("Usage: sshscan --db <FILE> [OPTIONS] history <list | export <id>>\n");
+                println!("  list           List stored scans");
+                println!("  export <id>    Write a report for a stored scan, honoring the report options");
                 return Err(ExitCode::SUCCESS);
             }
-            let cidr: String = pargs.free_from_str().map_err(|_| {
-                eprintln!("Error: 'scan' requires <cidr>");
+            if config.db.is_none() {
+                eprintln!("Error: 'history' requires --db <FILE>");
+                return Err(ExitCode::FAILURE);
+            }
+            let action: String = pargs.free_from_str().map_err(|_| {
+                eprintln!("Error: 'history' requires an action: list or export");
                 ExitCode::FAILURE
             })?;
-            let port: u16 = pargs.free_from_str().unwrap_or(22);
-            let aggressive: bool = pargs.free_from_str().unwrap_or(true);
-            Command::Scan {
-                cidr,
-                port,
-                aggressive,
+            match action.as_str() {
+                "list" => Command::History(HistoryAction::List),
+                "export" => {
+                    let id: i64 = pargs.free_from_str().map_err(|_| {
+                        eprintln!("Error: 'history export' requires a scan <id>, see 'history list'");
+                        ExitCode::FAILURE
+                    })?;
+                    Command::History(HistoryAction::Export { id })
+                }
+                other => {
+                    eprintln!("Error: unknown history action '{other}', expected list or export");
+                    return Err(ExitCode::FAILURE);
+                }
+            }
+        }
+        "serve" => {
+            if pargs.contains(["-h", "--help"]) {
+                println!("Usage: sshscan [OPTIONS] serve [--listen <ADDR>] [--interval <SECS>] [SCAN OPTIONS] <target>... [ports] [aggressive]\n");
+                println!("  --listen <ADDR>        Address to serve HTTP on [default: 127.0.0.1:9180]");
+                println!("  --interval <SECS>      Seconds to wait between scans [default: 3600]");
+                println!("{SCAN_SPEC_HELP}");
+                return Err(ExitCode::SUCCESS);
+            }
+            if site.is_some() || !matches!(config.output_file, OutputType::Stdout) {
+                eprintln!("Error: 'serve' does not write files, --site and --output cannot be used");
+                return Err(ExitCode::FAILURE);
+            }
+            let listen: String = pargs
+                .opt_value_from_str("--listen")
+                .map_err(arg_error)?
+                .unwrap_or_else(|| "127.0.0.1:9180".to_owned());
+            let interval: u64 = pargs.opt_value_from_str("--interval").map_err(arg_error)?.unwrap_or(3600);
+            let spec = scan_spec(&mut pargs, &config, "serve")?;
+            Command::Serve {
+                spec,
+                listen,
+                interval,
             }
         }
         other => {
@@ -139,73 +591,225 @@ pub fn main() -> ExitCode {
 
     match command {
         Command::Generate { input_file } => {
-            let data = match std::fs::read_to_string(&input_file) {
-                Ok(s) => s,
-                Err(err) => {
-                    eprintln!(
-                        "Failed to read input file at: {}",
-                        input_file.to_string_lossy()
-                    );
-                    eprintln!("Reason: {err}");
+            let (info, hosts) = match read_input(&input_file, config.include_down) {
+                Ok(scan) => scan,
+                Err(code) => return code,
+            };
+            generate(info, hosts, &config, None);
+            ExitCode::SUCCESS
+        }
+        Command::Check { input_file, baseline, save_baseline } => {
+            let (info, hosts) = match read_input(&input_file, config.include_down) {
+                Ok(scan) => scan,
+                Err(code) => return code,
+            };
+            let baseline = match baseline.map(|path| load_scan_file(&path, false).map_err(|e| (path, e))).transpose() {
+                Ok(baseline) => baseline,
+                Err((path, e)) => {
+                    eprintln!("Error: failed to load baseline from {}: {e}", path.to_string_lossy());
                     return ExitCode::FAILURE;
                 }
             };
-            let cur = std::io::Cursor::new(data);
-            generate(cur, &config);
+            let (info, hosts, violations) = generate(info, hosts, &config, baseline.as_ref().map(|(_, hosts)| hosts.as_slice()));
+            if let Some(path) = save_baseline {
+                let json = sshscan_core::json::generate(&hosts, &info, &sshscan_core::findings::collect(&hosts));
+                if let Err(e) = std::fs::write(&path, json) {
+                    eprintln!("Error: failed to save baseline to {}: {e}", path.to_string_lossy());
+                    return ExitCode::FAILURE;
+                }
+            }
+            if violations > 0 {
+                eprintln!("{violations} policy violation(s) found");
+                return ExitCode::from(VIOLATION);
+            }
             ExitCode::SUCCESS
         }
-        Command::Scan {
-            cidr,
-            port,
-            aggressive,
-        } => {
-            if let Err(e) = scan_and_gen(&cidr, port, aggressive, &config) {
+        Command::Scan(spec) => {
+            if let Err(e) = scan_and_gen(&spec, &config) {
                 eprintln!("Error: {e}");
                 return ExitCode::FAILURE;
             }
             ExitCode::SUCCESS
         }
+        Command::History(action) => history(&action, &config),
+        Command::Trend { inputs } => {
+            let mut scans = match stored_scans(&config) {
+                Ok(scans) => scans,
+                Err(e) => {
+                    eprintln!("Error: failed to read scans from the database: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            for input in inputs {
+                match load_scan_file(&input, config.include_down) {
+                    Ok(scan) => scans.push(scan),
+                    Err(e) => {
+                        eprintln!("Error: failed to load {}: {e}", input.to_string_lossy());
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            let trend = sshscan_core::trend::Trend::build(&scans);
+            let built_report = if config.format == OutputFormat::Markdown {
+                sshscan_core::markdown::generate_trend(&trend, &config.report_options)
+            } else {
+                sshscan_core::html::generate_trend(&trend, &config.report_options)
+            };
+            write_output(&built_report, &config);
+            ExitCode::SUCCESS
+        }
+        Command::Serve {
+            spec,
+            listen,
+            interval,
+        } => {
+            let scan = move || {
+                let (info, hosts) = scan(&spec, config.include_down)?;
+                let findings = collect_findings(&hosts, &config);
+                record(&config, &info, &hosts, &findings);
+                Ok(serve::Snapshot {
+                    html: render(&hosts, &info, &findings, &config, OutputFormat::Html),
+                    json: render(&hosts, &info, &findings, &config, OutputFormat::Json),
+                    metrics: render(&hosts, &info, &findings, &config, OutputFormat::Metrics),
+                })
+            };
+            if let Err(e) = serve::serve(&listen, std::time::Duration::from_secs(interval), scan) {
+                eprintln!("Error: failed to serve on {listen}: {e}");
+                return ExitCode::FAILURE;
+            }
+            ExitCode::SUCCESS
+        }
     }
 }
 
-fn scan_and_gen(
-    cidr: &str,
-    port: u16,
-    aggressive: bool,
-    config: &SshScanConfig,
-) -> Result<(), sshscan_core::SshScanErr> {
+fn scan_and_gen(spec: &ScanSpec, config: &SshScanConfig) -> Result<(), sshscan_core::SshScanErr> {
+    let (info, hosts) = scan(spec, config.include_down)?;
+    generate(info, hosts, config, None);
+    Ok(())
+}
+
+/// Scan the targets of `spec`, running nmap once per distinct set of ports and address family
+/// and merging the results
+fn scan(
+    spec: &ScanSpec,
+    include_down: bool,
+) -> Result<(sshscan_core::ScanInfo, Vec<sshscan_core::Host>), sshscan_core::SshScanErr> {
+    let mut runs: std::collections::BTreeMap<(&Ports, bool), Vec<String>> = std::collections::BTreeMap::new();
+    for target in &spec.targets {
+        let ports = target.ports.as_ref().unwrap_or(&spec.ports);
+        runs.entry((ports, spec.is_ipv6(&target.address))).or_default().extend(target.address.nmap_specs());
+    }
+    let mut info = sshscan_core::ScanInfo::default();
+    let mut hosts: Vec<sshscan_core::Host> = Vec::new();
+    for ((ports, ipv6), specs) in runs {
+        // nmap rejects exclusions of the other address family
+        let exclude: Vec<String> =
+            spec.exclude.iter().filter(|a| spec.is_ipv6(a) == ipv6).flat_map(Address::exclude_specs).collect();
+        let data = run_nmap(spec, &specs, &exclude, ports, ipv6)?;
+        let (run_info, run_hosts) = load_hosts(std::io::Cursor::new(data), include_down)?;
+        info.args = match (info.args.take(), run_info.args) {
+            (Some(prev), Some(args)) => Some(format!("{prev}; {args}")),
+            (prev, args) => prev.or(args),
+        };
+        info.nmap_version = info.nmap_version.or(run_info.nmap_version);
+        info.start = info.start.into_iter().chain(run_info.start).min();
+        info.end = info.end.into_iter().chain(run_info.end).max();
+        for host in run_hosts {
+            match hosts.iter_mut().find(|h| h.addr == host.addr) {
+                Some(existing) => {
+                    for hp in host.port_states {
+                        if !existing.port_states.iter().any(|e| e.portid == hp.portid) {
+                            existing.port_states.push(hp);
+                        }
+                    }
+                    for name in host.hostnames {
+                        if !existing.hostnames.contains(&name) {
+                            existing.hostnames.push(name);
+                        }
+                    }
+                }
+                None => hosts.push(host),
+            }
+        }
+    }
+    Ok((info, hosts))
+}
+
+/// Run nmap with the options of `spec` against `specs`, skipping `exclude`, and return its XML output
+fn run_nmap(spec: &ScanSpec, specs: &[String], exclude: &[String], ports: &Ports, ipv6: bool) -> Result<String, sshscan_core::SshScanErr> {
     use std::process::{Command as Cmd, Stdio};
-    let Ok(nmap_exe) = which::which("nmap") else {
-        return Err(sshscan_core::SshScanErr::Other(
-            "nmap not found in $PATH".to_owned(),
-        ));
-    };
-    let mut nmap_handle = Cmd::new(nmap_exe);
-    if aggressive {
-        nmap_handle.arg("-T5");
-    } else {
-        nmap_handle.arg("-T1");
+    let nmap_exe = which::which("nmap").map_err(|_| {
+        // which only finds executables, tell a file without execute permission apart from a missing one
+        std::env::var_os("PATH")
+            .and_then(|paths| std::env::split_paths(&paths).map(|dir| dir.join("nmap")).find(|path| path.is_file()))
+            .map_or(NmapError::NotFound, |path| NmapError::NotExecutable(path.to_string_lossy().into_owned()))
+    })?;
+    let mut nmap_handle = Cmd::new(&nmap_exe);
+    #[allow(clippy::needless_borrows_for_generic_args)]
+    nmap_handle.arg(&format!("-T{}", spec.timing));
+    if ipv6 {
+        nmap_handle.arg("-6");
     }
     nmap_handle.arg("-sV");
     #[allow(clippy::needless_borrows_for_generic_args)]
     // reduce binary size by sticking with just &str to Command::arg
-    nmap_handle.arg(&format!("-p{port}"));
+    nmap_handle.arg(&format!("-p{ports}"));
     nmap_handle.arg("--script");
-    nmap_handle.arg("ssh2-enum-algos");
-    nmap_handle.arg(cidr);
+    nmap_handle.arg("ssh2-enum-algos,ssh-hostkey,sshv1");
+    if let Some(script_args) = &spec.script_args {
+        nmap_handle.arg("--script-args");
+        nmap_handle.arg(script_args);
+    }
+    if spec.no_ping {
+        nmap_handle.arg("-Pn");
+    }
+    if let Some(source_port) = spec.source_port {
+        nmap_handle.arg("--source-port");
+        nmap_handle.arg(source_port.to_string());
+    }
+    if let Some(max_rate) = spec.max_rate {
+        nmap_handle.arg("--max-rate");
+        nmap_handle.arg(max_rate.to_string());
+    }
+    if !exclude.is_empty() {
+        nmap_handle.arg("--exclude");
+        nmap_handle.arg(exclude.join(","));
+    }
+    nmap_handle.args(&spec.nmap_args);
+    nmap_handle.args(specs);
     nmap_handle.arg("-oX");
     nmap_handle.arg("-");
-    nmap_handle.stdin(Stdio::null()).stdout(Stdio::piped());
-    let process_output = nmap_handle.spawn().unwrap().wait_with_output().unwrap();
-    let data = String::from_utf8(process_output.stdout).unwrap();
-    let cur = std::io::Cursor::new(data);
-    generate(cur, config);
-    Ok(())
+    nmap_handle.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let process_output = nmap_handle
+        .spawn()
+        .and_then(std::process::Child::wait_with_output)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => NmapError::NotExecutable(nmap_exe.to_string_lossy().into_owned()),
+            _ => NmapError::Spawn(e),
+        })?;
+    let stderr = String::from_utf8_lossy(&process_output.stderr);
+    if !process_output.status.success() {
+        return Err(NmapError::from_failure(process_output.status.code(), &stderr).into());
+    }
+    // Warnings such as unresolvable host names do not fail the scan, pass them on
+    for line in stderr.lines().filter(|line| !line.trim().is_empty()) {
+        eprintln!("nmap: {line}");
+    }
+    Ok(String::from_utf8(process_output.stdout).map_err(|_| NmapError::InvalidOutput)?)
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs().try_into().unwrap_or(i64::MAX))
 }
 
-fn generate(input: impl std::io::Read, config: &SshScanConfig) {
-    let res = sshscan_core::xml::process_xml(input, !config.include_down)
-        .expect("Failed to process XML!");
+/// Parse nmap XML, reporting hosts that could not be processed
+fn load_hosts(
+    input: impl std::io::Read,
+    include_down: bool,
+) -> Result<(sshscan_core::ScanInfo, Vec<sshscan_core::Host>), sshscan_core::SshScanErr> {
+    let (info, res) = sshscan_core::xml::process_xml_with_info(input, !include_down)?;
     let mut processed_hosts = Vec::with_capacity(res.len());
     for found in res {
         match found {
@@ -213,9 +817,128 @@ fn generate(input: impl std::io::Read, config: &SshScanConfig) {
             Err(e) => eprintln!("Error: {e:?}"),
         }
     }
-    let agg_data = sshscan_core::agg_data::AggregatedData::build_from_hosts(&processed_hosts);
-    let built_report = sshscan_core::html::generate(&processed_hosts, &agg_data);
+    Ok((info, processed_hosts))
+}
+
+fn key_changes(findings: &[sshscan_core::findings::Finding]) -> Vec<sshscan_core::findings::Finding> {
+    use sshscan_core::findings::FindingKind;
+    findings
+        .iter()
+        .filter(|f| matches!(f.kind, FindingKind::HostKeyChanged | FindingKind::RevokedHostKey))
+        .cloned()
+        .collect()
+}
+
+/// Findings of a scan, including host key changes against `--previous` and `--known-hosts`
+fn collect_findings(hosts: &[sshscan_core::Host], config: &SshScanConfig) -> Vec<sshscan_core::findings::Finding> {
+    let mut findings = sshscan_core::findings::collect(hosts);
+    if let Some(previous) = &config.previous {
+        findings.extend(sshscan_core::baseline::changed_since(previous, hosts));
+    }
+    findings.extend(sshscan_core::known_hosts::verify(&config.known_hosts, hosts));
+    findings
+}
+
+/// Render a single document report in `format`
+fn render(
+    hosts: &[sshscan_core::Host],
+    info: &sshscan_core::ScanInfo,
+    findings: &[sshscan_core::findings::Finding],
+    config: &SshScanConfig,
+    format: OutputFormat,
+) -> String {
+    let agg_data = sshscan_core::agg_data::AggregatedData::build_from_hosts(hosts);
+    let key_changes = key_changes(findings);
+    match (format, &config.template) {
+        (OutputFormat::Html, Some(template)) => {
+            let context = sshscan_core::template::build_context(hosts, &agg_data, info, findings, &config.report_options);
+            template.render(&context)
+        }
+        (OutputFormat::Html, None) => sshscan_core::html::generate(hosts, &agg_data, &config.client_profiles, &key_changes, &config.report_options),
+        (OutputFormat::Markdown, _) => sshscan_core::markdown::generate(hosts, &agg_data, &key_changes, &config.report_options),
+        (OutputFormat::Text, _) => {
+            use std::io::IsTerminal;
+            // Only colorize when a human is looking, honoring https://no-color.org
+            let color = matches!(config.output_file, OutputType::Stdout)
+                && std::io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+            sshscan_core::text::generate(hosts, &agg_data, &key_changes, color)
+        }
+        (OutputFormat::Sarif, _) => sshscan_core::sarif::generate(findings),
+        (OutputFormat::Junit, _) => sshscan_core::junit::generate(hosts, findings),
+        (OutputFormat::Metrics, _) => sshscan_core::metrics::generate(hosts, &agg_data, info, findings, unix_now()),
+        (OutputFormat::Json, _) => sshscan_core::json::generate(hosts, info, findings),
+        (OutputFormat::KnownHosts, _) => sshscan_core::known_hosts::generate(hosts, config.hash_known_hosts),
+        (OutputFormat::Sshfp, _) => sshscan_core::sshfp::generate(hosts),
+    }
+}
+
+/// Read and parse the nmap XML input file
+fn read_input(
+    input_file: &std::path::Path,
+    include_down: bool,
+) -> Result<(sshscan_core::ScanInfo, Vec<sshscan_core::Host>), ExitCode> {
+    let data = std::fs::read_to_string(input_file).map_err(|err| {
+        eprintln!(
+            "Failed to read input file at: {}",
+            input_file.to_string_lossy()
+        );
+        eprintln!("Reason: {err}");
+        ExitCode::FAILURE
+    })?;
+    load_hosts(std::io::Cursor::new(data), include_down).map_err(|e| {
+        eprintln!("Error: failed to process {}: {e}", input_file.to_string_lossy());
+        ExitCode::FAILURE
+    })
+}
+
+/// Write the report, returning the scan and the number of policy violations found.
+/// With a baseline only deviations from it are reported.
+fn generate(
+    info: sshscan_core::ScanInfo,
+    processed_hosts: Vec<sshscan_core::Host>,
+    config: &SshScanConfig,
+    baseline: Option<&[sshscan_core::Host]>,
+) -> (sshscan_core::ScanInfo, Vec<sshscan_core::Host>, usize) {
+    let mut findings = collect_findings(&processed_hosts, config);
+    record(config, &info, &processed_hosts, &findings);
+    if let Some(baseline) = baseline {
+        findings = sshscan_core::baseline::deviations(baseline, &processed_hosts, findings);
+    }
+    write_report(&processed_hosts, &info, &findings, config);
+    let violations = findings.iter().filter(|f| f.is_violation()).count();
+    (info, processed_hosts, violations)
+}
+
+/// Write the report in the configured format to the configured output
+fn write_report(
+    hosts: &[sshscan_core::Host],
+    info: &sshscan_core::ScanInfo,
+    findings: &[sshscan_core::findings::Finding],
+    config: &SshScanConfig,
+) {
+    if let (Some(grouping), OutputType::File(dir)) = (config.site, &config.output_file) {
+        let agg_data = sshscan_core::agg_data::AggregatedData::build_from_hosts(hosts);
+        let files = sshscan_core::html::generate_site(hosts, &agg_data, &config.client_profiles, &key_changes(findings), &config.report_options, grouping);
+        std::fs::create_dir_all(dir).expect("Failed to create output directory!");
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).expect("Failed to write all data");
+        }
+        return;
+    }
+    let built_report = render(hosts, info, findings, config, config.format);
+    write_output(&built_report, config);
+}
+
+fn write_output(built_report: &str, config: &SshScanConfig) {
     match &config.output_file {
+        OutputType::File(path) if config.format == OutputFormat::Metrics => {
+            // The textfile collector may read at any time, so never expose a partially written file
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".tmp");
+            std::fs::write(&tmp, built_report.as_bytes()).expect("Failed to write all data");
+            std::fs::rename(&tmp, path).expect("Failed to move metrics into place");
+        }
         OutputType::File(path) => {
             let mut writer = std::io::BufWriter::new(
                 std::fs::File::create(path).expect("Failed to open output file!"),
@@ -229,3 +952,99 @@ fn generate(input: impl std::io::Read, config: &SshScanConfig) {
         }
     }
 }
+
+/// Save the scan to the `--db` database, if any. Failing to record does not stop the report.
+#[cfg(feature = "sqlite")]
+fn record(
+    config: &SshScanConfig,
+    info: &sshscan_core::ScanInfo,
+    hosts: &[sshscan_core::Host],
+    findings: &[sshscan_core::findings::Finding],
+) {
+    let Some(db) = &config.db else { return };
+    let res = sshscan_core::history::Store::open(db).and_then(|mut store| store.save(info, hosts, findings, unix_now()));
+    if let Err(e) = res {
+        eprintln!("Error: failed to record scan in {}: {e}", db.to_string_lossy());
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+const fn record(
+    _config: &SshScanConfig,
+    _info: &sshscan_core::ScanInfo,
+    _hosts: &[sshscan_core::Host],
+    _findings: &[sshscan_core::findings::Finding],
+) {
+}
+
+/// Load a scan from nmap XML or sshscan JSON, told apart by the first character
+fn load_scan_file(
+    path: &std::path::Path,
+    include_down: bool,
+) -> Result<(sshscan_core::ScanInfo, Vec<sshscan_core::Host>), sshscan_core::SshScanErr> {
+    let data = std::fs::read_to_string(path).map_err(|e| sshscan_core::SshScanErr::Other(e.to_string()))?;
+    if data.trim_start().starts_with('{') {
+        sshscan_core::json::parse(&data)
+    } else {
+        load_hosts(std::io::Cursor::new(data), include_down)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn stored_scans(config: &SshScanConfig) -> Result<Vec<(sshscan_core::ScanInfo, Vec<sshscan_core::Host>)>, sshscan_core::SshScanErr> {
+    let Some(db) = &config.db else { return Ok(Vec::new()) };
+    let store = sshscan_core::history::Store::open(db)?;
+    store.list()?.iter().map(|scan| store.load(scan.id)).collect()
+}
+
+#[cfg(not(feature = "sqlite"))]
+#[allow(clippy::unnecessary_wraps)]
+const fn stored_scans(_config: &SshScanConfig) -> Result<Vec<(sshscan_core::ScanInfo, Vec<sshscan_core::Host>)>, sshscan_core::SshScanErr> {
+    Ok(Vec::new())
+}
+
+#[cfg(feature = "sqlite")]
+fn history(action: &HistoryAction, config: &SshScanConfig) -> ExitCode {
+    let Some(db) = &config.db else { return ExitCode::FAILURE };
+    let store = match sshscan_core::history::Store::open(db) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Error: failed to open {}: {e}", db.to_string_lossy());
+            return ExitCode::FAILURE;
+        }
+    };
+    let res = match action {
+        HistoryAction::List => store.list().map(|scans| {
+            println!("{:>6}  {:<20}  {:>6}  {:>6}  {:>8}  Arguments", "ID", "Started", "Hosts", "Open", "Findings");
+            for scan in scans {
+                let started = config
+                    .report_options
+                    .format_timestamp(scan.info.start.unwrap_or(scan.recorded))
+                    .unwrap_or_default();
+                println!(
+                    "{:>6}  {started:<20}  {:>6}  {:>6}  {:>8}  {}",
+                    scan.id,
+                    scan.hosts,
+                    scan.open_ports,
+                    scan.findings,
+                    scan.info.args.as_deref().unwrap_or(""),
+                );
+            }
+        }),
+        HistoryAction::Export { id } => store.load(*id).map(|(info, hosts)| {
+            let findings = collect_findings(&hosts, config);
+            write_report(&hosts, &info, &findings, config);
+        }),
+    };
+    if let Err(e) = res {
+        eprintln!("Error: {e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn history(_action: &HistoryAction, _config: &SshScanConfig) -> ExitCode {
+    eprintln!("Error: 'history' requires sshscan to be built with the sqlite feature");
+    ExitCode::FAILURE
+}
@@ -0,0 +1,137 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    panic::AssertUnwindSafe,
+    sync::{mpsc::{self, TrySendError}, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Rendered output of the latest completed scan
+pub struct Snapshot {
+    pub html: String,
+    pub json: String,
+    pub metrics: String,
+}
+
+/// The latest snapshot and when it was taken
+type Latest = Arc<Mutex<Option<(Instant, Arc<Snapshot>)>>>;
+
+const MAX_LINE: u64 = 8192;
+const MAX_HEADERS: usize = 100;
+/// Threads handling connections
+const WORKERS: usize = 8;
+/// Connections waiting for a free worker, any more are turned away with a 503
+const QUEUE: usize = 64;
+
+/// Run `scan` every `interval` on a background thread and serve its latest results on `listen`
+/// until the process is killed. Failed or panicking scans are logged and the previous results kept.
+pub fn serve(
+    listen: &str,
+    interval: Duration,
+    scan: impl Fn() -> Result<Snapshot, sshscan_core::SshScanErr> + Send + 'static,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("Serving on http://{}/", listener.local_addr()?);
+    let latest: Latest = Arc::default();
+    let scanner_latest = Arc::clone(&latest);
+    std::thread::spawn(move || {
+        loop {
+            match std::panic::catch_unwind(AssertUnwindSafe(&scan)) {
+                Ok(Ok(snapshot)) => {
+                    *scanner_latest.lock().unwrap_or_else(std::sync::PoisonError::into_inner) =
+                        Some((Instant::now(), Arc::new(snapshot)));
+                }
+                Ok(Err(e)) => eprintln!("Error: scan failed: {e}"),
+                Err(payload) => {
+                    let msg = payload
+                        .downcast_ref::<&str>()
+                        .copied()
+                        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                        .unwrap_or("unknown cause");
+                    eprintln!("Error: scan panicked: {msg}");
+                }
+            }
+            std::thread::sleep(interval);
+        }
+    });
+    let (queue, receiver) = mpsc::sync_channel::<TcpStream>(QUEUE);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..WORKERS {
+        let receiver = Arc::clone(&receiver);
+        let latest = Arc::clone(&latest);
+        std::thread::spawn(move || {
+            loop {
+                let stream = receiver.lock().unwrap_or_else(std::sync::PoisonError::into_inner).recv();
+                let Ok(stream) = stream else { return };
+                if let Err(e) = handle(stream, &latest) {
+                    eprintln!("Error: failed to handle request: {e}");
+                }
+            }
+        });
+    }
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        match queue.try_send(stream) {
+            Ok(()) => {}
+            Err(TrySendError::Full(stream)) => {
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let _ = respond(&mut &stream, "503 Service Unavailable", "text/plain", "Too many connections\n", false);
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+    Ok(())
+}
+
+fn handle(stream: TcpStream, latest: &Latest) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    (&mut reader).take(MAX_LINE).read_line(&mut request_line)?;
+    // Drain the headers, nothing in them is needed
+    let mut header = String::new();
+    for _ in 0..MAX_HEADERS {
+        header.clear();
+        if (&mut reader).take(MAX_LINE).read_line(&mut header)? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_ascii_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let path = target.split('?').next().unwrap_or_default();
+    let head = method == "HEAD";
+    let mut stream = &stream;
+    if !matches!(method, "GET" | "HEAD") {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", "Method not allowed\n", head);
+    }
+    let latest = latest.lock().unwrap_or_else(std::sync::PoisonError::into_inner).clone();
+    if path == "/healthz" {
+        let body = match &latest {
+            Some((scanned, _)) => format!("ok\nlast successful scan: {}s ago\n", scanned.elapsed().as_secs()),
+            None => "ok\nno scan has completed yet\n".to_owned(),
+        };
+        return respond(&mut stream, "200 OK", "text/plain", &body, head);
+    }
+    let Some((_, snapshot)) = latest else {
+        return respond(&mut stream, "503 Service Unavailable", "text/plain", "No scan has completed yet\n", head);
+    };
+    match path {
+        "/" | "/index.html" => respond(&mut stream, "200 OK", "text/html; charset=utf-8", &snapshot.html, head),
+        "/api/scan" => respond(&mut stream, "200 OK", "application/json", &snapshot.json, head),
+        "/metrics" => respond(&mut stream, "200 OK", "text/plain; version=0.0.4", &snapshot.metrics, head),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "Not found\n", head),
+    }
+}
+
+fn respond(stream: &mut impl Write, status: &str, content_type: &str, body: &str, head: bool) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len(),
+    )?;
+    if !head {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()
+}
@@ -0,0 +1,54 @@
+//! Helpers to run the sshscan binary against a fake nmap
+#![allow(dead_code)]
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// nmap XML output: OpenSSH and Dropbear on 10.0.0.1 and two hosts without SSH ports
+pub const SCAN_XML: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/scan.xml");
+
+/// A directory holding a fake `nmap` shell script, removed on drop
+pub struct FakeNmap {
+    dir: PathBuf,
+}
+
+impl FakeNmap {
+    /// An executable `nmap` running `script` with `/bin/sh`
+    pub fn new(name: &str, script: &str) -> Self {
+        Self::with_mode(name, script, 0o755)
+    }
+
+    /// An `nmap` that prints the fixture scan
+    pub fn ok(name: &str) -> Self {
+        Self::new(name, &format!("/bin/cat '{SCAN_XML}'"))
+    }
+
+    pub fn with_mode(name: &str, script: &str, mode: u32) -> Self {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("fake-nmap-{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let nmap = dir.join("nmap");
+        std::fs::write(&nmap, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&nmap, std::fs::Permissions::from_mode(mode)).unwrap();
+        Self { dir }
+    }
+
+    pub fn nmap(&self) -> PathBuf {
+        self.dir.join("nmap")
+    }
+
+    /// The sshscan binary with only the fake nmap on PATH
+    pub fn sshscan(&self) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_sshscan"));
+        cmd.env("PATH", &self.dir);
+        cmd
+    }
+}
+
+impl Drop for FakeNmap {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,112 @@
+#![cfg(unix)]
+mod common;
+
+use std::process::Output;
+
+use common::FakeNmap;
+
+fn scan(nmap: &FakeNmap) -> Output {
+    nmap.sshscan().args(["-f", "json", "scan", "10.0.0.1"]).output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn scans_with_nmap_output() {
+    let nmap = FakeNmap::ok("ok");
+    let output = scan(&nmap);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"10.0.0.1\""));
+}
+
+#[test]
+fn reports_missing_nmap() {
+    let nmap = FakeNmap::ok("missing");
+    std::fs::remove_file(nmap.nmap()).unwrap();
+    let output = scan(&nmap);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("nmap not found in $PATH"), "{}", stderr(&output));
+}
+
+#[test]
+fn reports_non_executable_nmap() {
+    let nmap = FakeNmap::with_mode("noexec", "exit 0", 0o644);
+    let output = scan(&nmap);
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("{} is not executable", nmap.nmap().display());
+    assert!(stderr(&output).contains(&expected), "{}", stderr(&output));
+}
+
+#[test]
+fn reports_failure_with_status_and_stderr() {
+    let nmap = FakeNmap::new("fail", "echo 'something broke' >&2\nexit 3");
+    let output = scan(&nmap);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr(&output);
+    assert!(stderr.contains("nmap exited with status 3:\nsomething broke"), "{stderr}");
+}
+
+#[test]
+fn reports_termination_by_signal() {
+    let nmap = FakeNmap::new("signal", "kill -9 $$");
+    let output = scan(&nmap);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("nmap was terminated by a signal"), "{}", stderr(&output));
+}
+
+#[test]
+fn reports_missing_privileges() {
+    let nmap = FakeNmap::new("privileges", "echo 'You requested a scan type which requires root privileges.' >&2\nexit 1");
+    let output = scan(&nmap);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr(&output);
+    assert!(stderr.contains("nmap needs more privileges for this scan, run it as root"), "{stderr}");
+    assert!(stderr.contains("requires root privileges"), "{stderr}");
+}
+
+#[test]
+fn reports_invalid_targets() {
+    let nmap = FakeNmap::new("resolve", "echo 'Failed to resolve \"nope.invalid\".' >&2\nexit 1");
+    let output = scan(&nmap);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr(&output);
+    assert!(stderr.contains("nmap rejected the targets"), "{stderr}");
+    assert!(stderr.contains("Failed to resolve \"nope.invalid\"."), "{stderr}");
+}
+
+#[test]
+fn passes_on_warnings_of_successful_scans() {
+    let script = format!("echo 'Failed to resolve \"nope.invalid\".' >&2\n/bin/cat '{}'", common::SCAN_XML);
+    let nmap = FakeNmap::new("warning", &script);
+    let output = scan(&nmap);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("nmap: Failed to resolve \"nope.invalid\"."), "{}", stderr(&output));
+}
+
+/// An `nmap` that prints its arguments, one per line, before the fixture scan
+fn echo_args(name: &str) -> FakeNmap {
+    FakeNmap::new(name, &format!("for arg in \"$@\"; do echo \"$arg\" >&2; done\n/bin/cat '{}'", common::SCAN_XML))
+}
+
+#[test]
+fn passes_pn_to_nmap() {
+    let nmap = echo_args("pn");
+    let output = nmap.sshscan().args(["-f", "json", "scan", "-Pn", "10.0.0.1"]).output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).lines().any(|line| line == "nmap: -Pn"), "{}", stderr(&output));
+}
+
+#[test]
+fn keeps_pn_option_values() {
+    let nmap = echo_args("pn-value");
+    let output = nmap.sshscan().args(["--title", "-Pn", "scan", "10.0.0.1"]).output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!stderr(&output).lines().any(|line| line == "nmap: -Pn"), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("<title>-Pn</title>"));
+
+    let output = nmap.sshscan().args(["-f", "json", "scan", "--nmap-arg", "-Pn", "10.0.0.1"]).output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stderr(&output).lines().filter(|line| *line == "nmap: -Pn").count(), 1, "{}", stderr(&output));
+}
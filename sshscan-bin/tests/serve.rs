@@ -0,0 +1,78 @@
+#![cfg(unix)]
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Child, Stdio},
+    time::{Duration, Instant},
+};
+
+use common::FakeNmap;
+
+/// Kills the server when the test ends, also on failure
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Send a GET request and return the status code and body
+fn get(addr: &str, path: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_ascii_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_owned())
+}
+
+#[test]
+fn serves_health_scan_and_metrics() {
+    let nmap = FakeNmap::ok("serve");
+    let child = nmap
+        .sshscan()
+        .args(["serve", "--listen", "127.0.0.1:0", "10.0.0.1"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut server = Server(child);
+    let mut stderr = BufReader::new(server.0.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Serving on http://")
+        .and_then(|rest| rest.strip_suffix('/'))
+        .unwrap_or_else(|| panic!("unexpected output: {line}"))
+        .to_owned();
+
+    let (status, health) = get(&addr, "/healthz");
+    assert_eq!(status, 200);
+    assert!(health.starts_with("ok\n"), "{health}");
+
+    // The first scan runs in the background, wait for it to complete
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let json = loop {
+        match get(&addr, "/api/scan") {
+            (200, body) => break body,
+            (503, _) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    };
+    assert!(json.contains("\"10.0.0.1\""), "{json}");
+    let (_, health) = get(&addr, "/healthz");
+    assert!(health.starts_with("ok\nlast successful scan: "), "{health}");
+
+    let (status, metrics) = get(&addr, "/metrics");
+    assert_eq!(status, 200);
+    assert!(metrics.contains("sshscan_host_grade{host=\"10.0.0.1\",port=\"22\"} 2"), "{metrics}");
+
+    assert_eq!(get(&addr, "/nope").0, 404);
+}
@@ -1,24 +1,42 @@
-use crate::Host;
+use crate::{Host, HostStatus, negotiate::{self, ClientProfile, Negotiated}, strength::{self, Grade, PreferenceWarning, preference_warnings}};
 use std::collections::HashMap;
 
+/// A single host:port offering an algorithm, along with the position (0 = first choice)
+/// the algorithm holds in that server's preference list.
+#[derive(Debug, Clone, Copy)]
+pub struct Offer<'host> {
+    pub host: &'host Host,
+    pub portid: u16,
+    pub rank: usize,
+}
+
+/// A single host:port, e.g. one of those sharing a HASSH fingerprint
+#[derive(Debug, Clone, Copy)]
+pub struct HostPort<'host> {
+    pub host: &'host Host,
+    pub portid: u16,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct AggregatedData<'host> {
-    kex: HashMap<String, Vec<&'host Host>>,
-    host_key: HashMap<String, Vec<&'host Host>>,
-    encryption: HashMap<String, Vec<&'host Host>>,
-    mac: HashMap<String, Vec<&'host Host>>,
-    compression: HashMap<String, Vec<&'host Host>>,
+    kex: HashMap<String, Vec<Offer<'host>>>,
+    host_key: HashMap<String, Vec<Offer<'host>>>,
+    encryption: HashMap<String, Vec<Offer<'host>>>,
+    mac: HashMap<String, Vec<Offer<'host>>>,
+    compression: HashMap<String, Vec<Offer<'host>>>,
+    hassh: HashMap<String, Vec<HostPort<'host>>>,
 }
 
 macro_rules! build {
     ($host:expr; $hp:expr; $res:expr; {$($nam:ident) +}) => {
         $(
-            for $nam in $hp.algos.$nam.iter() {
+            for (rank, $nam) in $hp.algos.$nam.iter().enumerate() {
+                let offer = Offer { host: $host, portid: $hp.portid, rank };
                 if let Some(existing) = $res.$nam.get_mut($nam) {
-                    existing.push($host);
+                    existing.push(offer);
                 }
                 else {
-                    $res.$nam.insert($nam.to_owned(), vec![$host]);
+                    $res.$nam.insert($nam.to_owned(), vec![offer]);
                 }
             }
         )*
@@ -33,14 +51,85 @@ impl<'host> AggregatedData<'host> {
         for host in hosts {
             for host_port in &host.port_states {
                 build!(host; host_port; res);
+                if let Some(hassh) = &host_port.hassh {
+                    res.hassh.entry(hassh.clone()).or_default().push(HostPort { host, portid: host_port.portid });
+                }
+            }
+        }
+        res
+    }
+
+    /// Host:ports per HASSH fingerprint
+    #[must_use]
+    pub fn hassh(&self) -> &HashMap<String, Vec<HostPort<'host>>> {
+        &self.hassh
+    }
+}
+
+/// Scan wide counts shown in report summaries
+#[derive(Debug, Default, Clone)]
+pub struct Summary<'host> {
+    pub hosts_up: usize,
+    pub hosts_down: usize,
+    pub hosts_unknown: usize,
+    pub ports_open: usize,
+    pub ports_closed: usize,
+    /// Host:ports per grade, indexed like [`Grade::ALL`]
+    pub grades: [usize; 5],
+    /// Open host:ports per product, `"Unknown"` when nmap could not identify it
+    pub products: HashMap<&'host str, usize>,
+}
+
+impl<'host> Summary<'host> {
+    #[must_use]
+    pub fn build_from_hosts(hosts: &'host [Host]) -> Self {
+        let mut res = Self::default();
+        for host in hosts {
+            match host.status {
+                HostStatus::Up => res.hosts_up += 1,
+                HostStatus::Down => res.hosts_down += 1,
+                HostStatus::Unknown => res.hosts_unknown += 1,
+            }
+            for host_port in &host.port_states {
+                if host_port.state {
+                    res.ports_open += 1;
+                    *res.products.entry(host_port.product.as_deref().unwrap_or("Unknown")).or_default() += 1;
+                } else {
+                    res.ports_closed += 1;
+                }
+                if let Some(grade) = strength::grade(&host_port.algos) {
+                    res.grades[grade as usize] += 1;
+                }
             }
         }
         res
     }
+
+    /// Products sorted by descending count, then name
+    #[must_use]
+    pub fn products_by_count(&self) -> Vec<(&'host str, usize)> {
+        let mut products: Vec<(&str, usize)> = self.products.iter().map(|(p, c)| (*p, *c)).collect();
+        products.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        products
+    }
+}
+
+/// Algorithms of one category sorted by descending count, then name
+#[must_use]
+pub fn by_count<'a, 'host>(list: &'a HashMap<String, Vec<Offer<'host>>>) -> Vec<(&'a String, &'a Vec<Offer<'host>>)> {
+    let mut sorted: Vec<_> = list.iter().collect();
+    sorted.sort_unstable_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+    sorted
+}
+
+/// Number of host:ports listing an algorithm as their first choice
+#[must_use]
+pub fn first_choice_count(offers: &[Offer<'_>]) -> usize {
+    offers.iter().filter(|o| o.rank == 0).count()
 }
 
 impl<'host> std::ops::Index<&'static str> for AggregatedData<'host> {
-    type Output = HashMap<String, Vec<&'host Host>>;
+    type Output = HashMap<String, Vec<Offer<'host>>>;
 
     fn index(&self, index: &'static str) -> &Self::Output {
         match index {
@@ -58,15 +147,19 @@ impl<'host> std::ops::Index<&'static str> for AggregatedData<'host> {
 pub(crate) struct HostTableView {
     pub port: u16,
     pub product: Option<String>,
+    pub hassh: Option<String>,
+    pub grade: Option<Grade>,
     pub algos: Vec<Vec<String>>,
+    pub warnings: Vec<PreferenceWarning>,
+    pub negotiations: Vec<Negotiated>,
 }
 
-pub(crate) fn wrangle_host_to_table(host: &Host) -> Vec<HostTableView> {
+pub(crate) fn wrangle_host_to_table(host: &Host, profiles: &[ClientProfile]) -> Vec<HostTableView> {
     let mut res: Vec<HostTableView> = Vec::new();
     for port in &host.port_states {
-        let mut inner: Vec<Vec<String>> = Vec::with_capacity(5);
-        inner.resize_with(5, Default::default);
         let longest = port.algos.longest();
+        // One row per preference rank, so the row index + 1 is the rank shown in the table
+        let mut inner: Vec<Vec<String>> = Vec::with_capacity(longest);
         for i in 0..longest {
             // Weird transpose from
             // A B C
@@ -83,7 +176,15 @@ pub(crate) fn wrangle_host_to_table(host: &Host) -> Vec<HostTableView> {
                 port.algos.compression.get(i).unwrap_or(&String::new()).clone(),
             ]);
         }
-        res.push(HostTableView { port: port.portid, product: port.product.clone(), algos: inner });
+        res.push(HostTableView {
+            port: port.portid,
+            product: port.product.clone(),
+            hassh: port.hassh.clone(),
+            grade: strength::grade(&port.algos),
+            algos: inner,
+            warnings: preference_warnings(&port.algos),
+            negotiations: if longest == 0 { Vec::new() } else { profiles.iter().map(|p| negotiate::negotiate(&port.algos, p)).collect() },
+        });
     }
     res
 }
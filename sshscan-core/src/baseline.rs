@@ -0,0 +1,136 @@
+use std::collections::{HashMap, HashSet};
+use crate::{findings::{self, Finding, FindingKind}, host_port, Host, HostKey};
+
+/// Compare host keys of a host:port against the expected ones. A fingerprint change for a key type
+/// present in both is reported, as is a completely different set of key types. Nothing is reported
+/// when either side has no keys, as the scan may just not have collected them.
+#[must_use]
+pub fn host_key_changes(addr: &str, portid: u16, expected: &[HostKey], actual: &[HostKey]) -> Vec<Finding> {
+    let mut res = Vec::new();
+    if expected.is_empty() || actual.is_empty() {
+        return res;
+    }
+    let mut push = |subject: &str, message: String| {
+        res.push(Finding {
+            addr: addr.to_owned(),
+            portid,
+            kind: FindingKind::HostKeyChanged,
+            severity: FindingKind::HostKeyChanged.default_severity(),
+            subject: subject.to_owned(),
+            message,
+        });
+    };
+    let mut shared_type = false;
+    for key in actual {
+        let same_type: Vec<&HostKey> = expected.iter().filter(|e| e.key_type == key.key_type).collect();
+        if same_type.is_empty() {
+            continue;
+        }
+        shared_type = true;
+        if !same_type.iter().any(|e| e.fingerprint == key.fingerprint) {
+            push(
+                &key.key_type,
+                format!("{} host key changed from {} to {}", key.key_type, same_type[0].fingerprint, key.fingerprint),
+            );
+        }
+    }
+    if !shared_type {
+        let types = |keys: &[HostKey]| keys.iter().map(|k| k.key_type.as_str()).collect::<Vec<_>>().join(", ");
+        push(&types(actual), format!("Host key types changed from {} to {}", types(expected), types(actual)));
+    }
+    res
+}
+
+/// Host key changes of every open host:port also open in a `previous` scan
+#[must_use]
+pub fn changed_since(previous: &[Host], current: &[Host]) -> Vec<Finding> {
+    let previous_keys = open_port_keys(previous);
+    current
+        .iter()
+        .flat_map(|h| h.port_states.iter().filter(|hp| hp.state).map(move |hp| (h, hp)))
+        .filter_map(|(h, hp)| previous_keys.get(&(h.addr.as_str(), hp.portid)).map(|expected| host_key_changes(&h.addr, hp.portid, expected, &hp.host_keys)))
+        .flatten()
+        .collect()
+}
+
+fn open_port_keys(hosts: &[Host]) -> HashMap<(&str, u16), &[HostKey]> {
+    hosts
+        .iter()
+        .flat_map(|h| h.port_states.iter().filter(|hp| hp.state).map(move |hp| ((h.addr.as_str(), hp.portid), hp.host_keys.as_slice())))
+        .collect()
+}
+
+/// Keep only deviations from an accepted baseline scan: findings the baseline did not have,
+/// open SSH ports it did not have, and changed host keys. Each finding is reported once, also when
+/// `findings` already holds the key changes, e.g. with the baseline given as previous scan too.
+#[must_use]
+pub fn deviations(baseline: &[Host], current: &[Host], findings: Vec<Finding>) -> Vec<Finding> {
+    let known: HashSet<(String, u16, FindingKind, String)> = findings::collect(baseline)
+        .into_iter()
+        .map(|f| (f.addr, f.portid, f.kind, f.subject))
+        .collect();
+    let baseline_ports = open_port_keys(baseline);
+    let mut res: Vec<Finding> = findings
+        .into_iter()
+        .filter(|f| !known.contains(&(f.addr.clone(), f.portid, f.kind, f.subject.clone())))
+        .collect();
+    res.extend(changed_since(baseline, current));
+    for host in current {
+        for hp in host.port_states.iter().filter(|hp| hp.state) {
+            if !baseline_ports.contains_key(&(host.addr.as_str(), hp.portid)) {
+                res.push(Finding {
+                    addr: host.addr.clone(),
+                    portid: hp.portid,
+                    kind: FindingKind::NewHost,
+                    severity: FindingKind::NewHost.default_severity(),
+                    subject: host_port(&host.addr, hp.portid),
+                    message: format!("{} is not in the baseline", host_port(&host.addr, hp.portid)),
+                });
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    res.retain(|f| seen.insert((f.addr.clone(), f.portid, f.kind, f.subject.clone())));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn key(fingerprint: &str) -> HostKey {
+        HostKey { key_type: "ssh-ed25519".to_owned(), bits: Some(256), fingerprint: fingerprint.to_owned(), key: None }
+    }
+
+    fn server(addr: &str, encryption: &str, fingerprint: &str) -> Host {
+        let mut port = fixtures::port(22, fixtures::algos(["curve25519-sha256", "ssh-ed25519", encryption, "hmac-md5", "none"]));
+        port.host_keys.push(key(fingerprint));
+        fixtures::host(addr, vec![port])
+    }
+
+    #[test]
+    fn keeps_only_deviations() {
+        let baseline = vec![server("10.0.0.1", "aes128-ctr", "aa")];
+        let current = vec![server("10.0.0.1", "aes128-ctr,3des-cbc", "bb"), server("10.0.0.2", "aes128-ctr", "cc")];
+        // Findings as collected with the baseline also given as the previous scan
+        let mut findings = findings::collect(&current);
+        findings.extend(changed_since(&baseline, &current));
+
+        let res = deviations(&baseline, &current, findings);
+        let mut found: Vec<(&str, FindingKind, &str)> = res.iter().map(|f| (f.addr.as_str(), f.kind, f.subject.as_str())).collect();
+        found.sort_unstable();
+        assert_eq!(found, [
+            ("10.0.0.1", FindingKind::WeakEncryption, "3des-cbc"),
+            ("10.0.0.1", FindingKind::HostKeyChanged, "ssh-ed25519"),
+            ("10.0.0.2", FindingKind::WeakMac, "hmac-md5"),
+            ("10.0.0.2", FindingKind::NewHost, "10.0.0.2:22"),
+        ]);
+    }
+
+    #[test]
+    fn unchanged_scan_has_no_deviations() {
+        let baseline = vec![server("10.0.0.1", "aes128-ctr", "aa")];
+        assert!(deviations(&baseline, &baseline, findings::collect(&baseline)).is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+use crate::strength::{Grade, Strength};
+
+const WIDTH: usize = 580;
+const LABEL_WIDTH: usize = 290;
+const VALUE_WIDTH: usize = 50;
+const BAR_HEIGHT: usize = 18;
+const BAR_GAP: usize = 6;
+const TITLE_HEIGHT: usize = 28;
+const LABEL_MAX_CHARS: usize = 40;
+
+pub(crate) struct Bar {
+    pub label: String,
+    pub value: usize,
+    pub color: &'static str,
+}
+
+pub(crate) const fn strength_color(strength: Strength) -> &'static str {
+    match strength {
+        Strength::Strong => "#2e7d32",
+        Strength::Weak => "#f9a825",
+        Strength::Broken => "#c62828",
+        Strength::Unknown => "#757575",
+    }
+}
+
+pub(crate) const fn grade_color(grade: Grade) -> &'static str {
+    match grade {
+        Grade::A => "#2e7d32",
+        Grade::B => "#7cb342",
+        Grade::C => "#f9a825",
+        Grade::D => "#ef6c00",
+        Grade::F => "#c62828",
+    }
+}
+
+/// Render a horizontal bar chart as an inline SVG element.
+pub(crate) fn bar_chart(title: &str, bars: &[Bar]) -> String {
+    let height = TITLE_HEIGHT + bars.len().max(1) * (BAR_HEIGHT + BAR_GAP);
+    let max = bars.iter().map(|b| b.value).max().unwrap_or(0).max(1);
+    let bar_space = WIDTH - LABEL_WIDTH - VALUE_WIDTH;
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        "<svg class=\"sshscan-chart\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{height}\" viewBox=\"0 0 {WIDTH} {height}\" role=\"img\" aria-label=\"{title}\">\
+        <text x=\"0\" y=\"18\" font-size=\"16\" font-weight=\"bold\">{title}</text>",
+        title = build_html::escape_html(title),
+    );
+    if bars.is_empty() {
+        let _ = write!(svg, "<text x=\"0\" y=\"{}\" font-size=\"12\">No data</text>", TITLE_HEIGHT + BAR_HEIGHT - 4);
+    }
+    for (i, bar) in bars.iter().enumerate() {
+        let y = TITLE_HEIGHT + i * (BAR_HEIGHT + BAR_GAP);
+        let len = (bar.value * bar_space).div_ceil(max);
+        let label = build_html::escape_html(&bar.label);
+        let short_label = if bar.label.chars().count() > LABEL_MAX_CHARS {
+            let truncated: String = bar.label.chars().take(LABEL_MAX_CHARS - 1).collect();
+            build_html::escape_html(&format!("{truncated}\u{2026}"))
+        } else {
+            label.clone()
+        };
+        let _ = write!(
+            svg,
+            "<text x=\"{lx}\" y=\"{ty}\" font-size=\"12\" text-anchor=\"end\" font-family=\"monospace\">{short_label}</text>\
+            <rect x=\"{LABEL_WIDTH}\" y=\"{y}\" width=\"{len}\" height=\"{BAR_HEIGHT}\" fill=\"{color}\"><title>{label}: {value}</title></rect>\
+            <text x=\"{vx}\" y=\"{ty}\" font-size=\"12\">{value}</text>",
+            lx = LABEL_WIDTH - 6,
+            ty = y + BAR_HEIGHT - 5,
+            vx = LABEL_WIDTH + len + 4,
+            color = bar.color,
+            value = bar.value,
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+pub(crate) struct Series {
+    pub label: String,
+    pub values: Vec<usize>,
+    pub color: &'static str,
+}
+
+/// Render a line chart with one line per series over the same x positions as an inline SVG element.
+pub(crate) fn line_chart(title: &str, x_labels: &[String], series: &[Series]) -> String {
+    const PLOT_LEFT: usize = 40;
+    const PLOT_HEIGHT: usize = 200;
+    const LEGEND_WIDTH: usize = 90;
+    let plot_width = WIDTH - PLOT_LEFT - LEGEND_WIDTH;
+    let height = TITLE_HEIGHT + PLOT_HEIGHT + 2 * BAR_HEIGHT;
+    let max = series.iter().flat_map(|s| s.values.iter().copied()).max().unwrap_or(0).max(1);
+    let last = x_labels.len().saturating_sub(1).max(1);
+    let x = |i: usize| PLOT_LEFT + i * plot_width / last;
+    let y = |v: usize| TITLE_HEIGHT + PLOT_HEIGHT - v * PLOT_HEIGHT / max;
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        "<svg class=\"sshscan-chart\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{height}\" viewBox=\"0 0 {WIDTH} {height}\" role=\"img\" aria-label=\"{title}\">\
+        <text x=\"0\" y=\"18\" font-size=\"16\" font-weight=\"bold\">{title}</text>\
+        <line x1=\"{PLOT_LEFT}\" y1=\"{bottom}\" x2=\"{right}\" y2=\"{bottom}\" stroke=\"#9e9e9e\"/>\
+        <text x=\"{label_x}\" y=\"{top}\" font-size=\"12\" text-anchor=\"end\">{max}</text>\
+        <text x=\"{label_x}\" y=\"{bottom}\" font-size=\"12\" text-anchor=\"end\">0</text>",
+        title = build_html::escape_html(title),
+        bottom = y(0),
+        top = y(max) + 10,
+        right = PLOT_LEFT + plot_width,
+        label_x = PLOT_LEFT - 6,
+    );
+    for (i, label) in x_labels.iter().enumerate() {
+        let _ = write!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"middle\">{}</text>",
+            x(i),
+            y(0) + BAR_HEIGHT,
+            build_html::escape_html(label),
+        );
+    }
+    for (n, s) in series.iter().enumerate() {
+        let points: Vec<String> = s.values.iter().enumerate().map(|(i, v)| format!("{},{}", x(i), y(*v))).collect();
+        let label = build_html::escape_html(&s.label);
+        let _ = write!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"><title>{label}</title></polyline>\
+            <rect x=\"{lx}\" y=\"{ly}\" width=\"12\" height=\"12\" fill=\"{color}\"/>\
+            <text x=\"{tx}\" y=\"{ty}\" font-size=\"12\">{label}</text>",
+            points.join(" "),
+            color = s.color,
+            lx = WIDTH - LEGEND_WIDTH + 10,
+            ly = TITLE_HEIGHT + n * (BAR_HEIGHT + BAR_GAP),
+            tx = WIDTH - LEGEND_WIDTH + 28,
+            ty = TITLE_HEIGHT + n * (BAR_HEIGHT + BAR_GAP) + 11,
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreads_many_points_over_the_plot() {
+        let labels: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        let series = [Series { label: "hosts".to_owned(), values: vec![1; 1000], color: "#000" }];
+        let svg = line_chart("Trend", &labels, &series);
+        let points = svg.split("points=\"").nth(1).and_then(|rest| rest.split('"').next()).unwrap();
+        let xs: Vec<&str> = points.split(' ').map(|p| p.split(',').next().unwrap()).collect();
+        assert_eq!(xs.len(), 1000);
+        assert_eq!((xs[0], xs[999]), ("40", "490"));
+        assert_ne!(xs[1], xs[999]);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{Algos, Host, strength::{self, Strength}};
+
+/// Severity of a finding, mirroring the usual note/warning/error levels of report formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl core::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FindingKind {
+    WeakKex,
+    WeakHostKey,
+    WeakEncryption,
+    WeakMac,
+    WeakCompression,
+    WeakPreference,
+    SshV1,
+    Terrapin,
+    OutdatedVersion,
+    NewHost,
+    HostKeyChanged,
+    RevokedHostKey,
+}
+
+impl FindingKind {
+    pub const ALL: [Self; 12] = [
+        Self::WeakKex,
+        Self::WeakHostKey,
+        Self::WeakEncryption,
+        Self::WeakMac,
+        Self::WeakCompression,
+        Self::WeakPreference,
+        Self::SshV1,
+        Self::Terrapin,
+        Self::OutdatedVersion,
+        Self::NewHost,
+        Self::HostKeyChanged,
+        Self::RevokedHostKey,
+    ];
+
+    /// Stable identifier, suitable for rule ids in machine readable output
+    #[must_use]
+    pub const fn id(self) -> &'static str {
+        match self {
+            Self::WeakKex => "weak-kex",
+            Self::WeakHostKey => "weak-host-key",
+            Self::WeakEncryption => "weak-encryption",
+            Self::WeakMac => "weak-mac",
+            Self::WeakCompression => "weak-compression",
+            Self::WeakPreference => "weak-preference",
+            Self::SshV1 => "sshv1",
+            Self::Terrapin => "terrapin",
+            Self::OutdatedVersion => "outdated-version",
+            Self::NewHost => "new-host",
+            Self::HostKeyChanged => "host-key-changed",
+            Self::RevokedHostKey => "revoked-host-key",
+        }
+    }
+
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::WeakKex => "Weak or broken key exchange algorithm offered",
+            Self::WeakHostKey => "Weak or broken host key algorithm offered",
+            Self::WeakEncryption => "Weak or broken encryption algorithm offered",
+            Self::WeakMac => "Weak or broken MAC algorithm offered",
+            Self::WeakCompression => "Weak compression algorithm offered",
+            Self::WeakPreference => "Weaker algorithm preferred over a stronger one",
+            Self::SshV1 => "SSH protocol version 1 supported",
+            Self::Terrapin => "Vulnerable to the Terrapin prefix truncation attack (CVE-2023-48795)",
+            Self::OutdatedVersion => "Outdated SSH server software",
+            Self::NewHost => "SSH server not present in the baseline",
+            Self::HostKeyChanged => "Host key type or fingerprint changed",
+            Self::RevokedHostKey => "Host key marked @revoked in known_hosts",
+        }
+    }
+
+    /// Severity used for the kind in rule metadata; individual findings may differ
+    #[must_use]
+    pub const fn default_severity(self) -> Severity {
+        match self {
+            Self::Terrapin | Self::NewHost => Severity::Warning,
+            // Weak algorithms are still offered by stock servers, and distributions backport fixes
+            // without changing the version, so neither fails a check on its own
+            Self::WeakCompression | Self::WeakPreference | Self::OutdatedVersion => Severity::Note,
+            Self::WeakKex
+            | Self::WeakHostKey
+            | Self::WeakEncryption
+            | Self::WeakMac
+            | Self::SshV1
+            | Self::HostKeyChanged
+            | Self::RevokedHostKey => Severity::Error,
+        }
+    }
+
+    const fn for_category(category: &str) -> Option<Self> {
+        Some(match category.as_bytes() {
+            b"kex_algorithms" => Self::WeakKex,
+            b"server_host_key_algorithms" => Self::WeakHostKey,
+            b"encryption_algorithms" => Self::WeakEncryption,
+            b"mac_algorithms" => Self::WeakMac,
+            b"compression_algorithms" => Self::WeakCompression,
+            _ => return None,
+        })
+    }
+}
+
+impl core::fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// A single issue found on a host:port
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub addr: String,
+    pub portid: u16,
+    pub kind: FindingKind,
+    pub severity: Severity,
+    /// The algorithm (or other item) the finding is about
+    pub subject: String,
+    pub message: String,
+}
+
+impl Finding {
+    /// Warnings and errors violate policy, notes are informational
+    #[must_use]
+    pub fn is_violation(&self) -> bool {
+        self.severity >= Severity::Warning
+    }
+}
+
+/// Collect findings for every host:port
+#[must_use]
+pub fn collect(hosts: &[Host]) -> Vec<Finding> {
+    let mut res = Vec::new();
+    for host in hosts {
+        for host_port in &host.port_states {
+            collect_algos(&host.addr, host_port.portid, &host_port.algos, &mut res);
+            let mut push = |kind: FindingKind, subject: &str, message: String| {
+                res.push(Finding {
+                    addr: host.addr.clone(),
+                    portid: host_port.portid,
+                    kind,
+                    severity: kind.default_severity(),
+                    subject: subject.to_owned(),
+                    message,
+                });
+            };
+            if host_port.sshv1 {
+                push(FindingKind::SshV1, "SSH-1", "SSH protocol version 1 is supported".to_owned());
+            }
+            if let Some(reason) = terrapin(&host_port.algos) {
+                push(FindingKind::Terrapin, "CVE-2023-48795", format!("Vulnerable to Terrapin: {reason} offered without strict key exchange"));
+            }
+            if let Some(product) = &host_port.product
+                && let Some(minimum) = outdated(product)
+            {
+                push(FindingKind::OutdatedVersion, product, format!("{product} is older than {minimum}"));
+            }
+        }
+    }
+    res
+}
+
+fn collect_algos(addr: &str, portid: u16, algos: &Algos, res: &mut Vec<Finding>) {
+    for category in Algos::KEYS {
+        let Some(kind) = FindingKind::for_category(category) else { continue };
+        for algo in &algos[category] {
+            let (severity, rating) = match strength::classify(category, algo) {
+                Strength::Broken => (Severity::Error, "Broken"),
+                Strength::Weak => (Severity::Note, "Weak"),
+                Strength::Strong | Strength::Unknown => continue,
+            };
+            res.push(Finding {
+                addr: addr.to_owned(),
+                portid,
+                kind,
+                severity,
+                subject: algo.clone(),
+                message: format!("{rating} algorithm {algo} offered"),
+            });
+        }
+    }
+    for warning in strength::preference_warnings(algos) {
+        res.push(Finding {
+            addr: addr.to_owned(),
+            portid,
+            kind: FindingKind::WeakPreference,
+            severity: Severity::Note,
+            subject: warning.preferred.clone(),
+            message: warning.to_string(),
+        });
+    }
+}
+
+/// Check for CVE-2023-48795: ChaCha20-Poly1305 or CBC combined with an EtM MAC is exploitable
+/// unless the server supports strict key exchange. Returns the offending mode.
+fn terrapin(algos: &Algos) -> Option<&'static str> {
+    if algos["kex_algorithms"].iter().any(|a| a == "kex-strict-s-v00@openssh.com") {
+        return None;
+    }
+    let encryption = &algos["encryption_algorithms"];
+    if encryption.iter().any(|a| a == "chacha20-poly1305@openssh.com") {
+        return Some("chacha20-poly1305@openssh.com");
+    }
+    let cbc = encryption.iter().any(|a| a.ends_with("-cbc"));
+    let etm = algos["mac_algorithms"].iter().any(|a| a.ends_with("-etm@openssh.com"));
+    (cbc && etm).then_some("CBC with an encrypt-then-MAC MAC")
+}
+
+/// Oldest releases not considered outdated, as (product prefix, minimum version)
+const MINIMUM_VERSIONS: [(&str, &str); 2] = [("OpenSSH ", "9.8"), ("Dropbear sshd ", "2024.84")];
+
+/// Returns the minimum version if `product` (as reported by nmap) is a known server older than it
+fn outdated(product: &str) -> Option<&'static str> {
+    MINIMUM_VERSIONS.iter().find_map(|&(prefix, minimum)| {
+        let version = product.strip_prefix(prefix)?;
+        (parse_version(version)? < parse_version(minimum)?).then_some(minimum)
+    })
+}
+
+/// Parse the leading dotted number of a version string, e.g. "9.6p1 Ubuntu" to [9, 6]
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let end = version.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(version.len());
+    let parts: Option<Vec<u32>> = version[..end].split('.').map(|p| p.parse().ok()).collect();
+    parts.filter(|p| !p.is_empty())
+}
@@ -0,0 +1,291 @@
+//! SQLite storage of scan history.
+//!
+//! The schema is stable and versioned with `PRAGMA user_version`, so the database can be queried
+//! directly, e.g. to find when a host stopped offering an algorithm:
+//!
+//! ```sql
+//! SELECT max(scans.start) FROM algorithms
+//! JOIN ports ON ports.id = algorithms.port_id
+//! JOIN hosts ON hosts.id = ports.host_id
+//! JOIN scans ON scans.id = hosts.scan_id
+//! WHERE hosts.addr = '10.0.0.1' AND algorithms.name = 'hmac-sha1';
+//! ```
+
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::{findings::Finding, Algos, Description, Host, HostKey, HostStatus, ScanInfo, SshScanErr};
+
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE scans (
+    id INTEGER PRIMARY KEY,
+    recorded INTEGER NOT NULL,
+    args TEXT,
+    nmap_version TEXT,
+    start INTEGER,
+    end INTEGER
+);
+CREATE TABLE hosts (
+    id INTEGER PRIMARY KEY,
+    scan_id INTEGER NOT NULL REFERENCES scans(id) ON DELETE CASCADE,
+    addr TEXT NOT NULL,
+    status TEXT NOT NULL
+);
+CREATE TABLE ports (
+    id INTEGER PRIMARY KEY,
+    host_id INTEGER NOT NULL REFERENCES hosts(id) ON DELETE CASCADE,
+    port INTEGER NOT NULL,
+    open INTEGER NOT NULL,
+    product TEXT,
+    hassh TEXT,
+    sshv1 INTEGER NOT NULL
+);
+CREATE TABLE algorithms (
+    port_id INTEGER NOT NULL REFERENCES ports(id) ON DELETE CASCADE,
+    category TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (port_id, category, rank)
+);
+CREATE TABLE findings (
+    scan_id INTEGER NOT NULL REFERENCES scans(id) ON DELETE CASCADE,
+    addr TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+CREATE TABLE host_keys (
+    port_id INTEGER NOT NULL REFERENCES ports(id) ON DELETE CASCADE,
+    type TEXT NOT NULL,
+    bits INTEGER,
+    fingerprint TEXT NOT NULL,
+    key TEXT
+);
+CREATE TABLE host_names (
+    host_id INTEGER NOT NULL REFERENCES hosts(id) ON DELETE CASCADE,
+    name TEXT NOT NULL
+);
+CREATE INDEX hosts_scan ON hosts(scan_id);
+CREATE INDEX hosts_addr ON hosts(addr);
+CREATE INDEX ports_host ON ports(host_id);
+CREATE INDEX host_keys_port ON host_keys(port_id);
+CREATE INDEX host_names_host ON host_names(host_id);
+CREATE INDEX algorithms_name ON algorithms(name);
+CREATE INDEX findings_scan ON findings(scan_id);
+";
+
+/// A stored scan, as listed by [`Store::list`]
+#[derive(Debug, Clone)]
+pub struct ScanRecord {
+    pub id: i64,
+    /// Unix timestamp the scan was saved
+    pub recorded: i64,
+    pub info: ScanInfo,
+    pub hosts: usize,
+    pub open_ports: usize,
+    pub findings: usize,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open the database at `path`, creating it and the schema when missing
+    pub fn open(path: &std::path::Path) -> Result<Self, SshScanErr> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        match version {
+            0 => {
+                conn.execute_batch(SCHEMA)?;
+                conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+            }
+            SCHEMA_VERSION => {}
+            other => return Err(SshScanErr::Storage(format!("unsupported schema version {other}, expected {SCHEMA_VERSION}"))),
+        }
+        Ok(Self { conn })
+    }
+
+    /// Save a scan with its findings, returning its id
+    pub fn save(&mut self, info: &ScanInfo, hosts: &[Host], findings: &[Finding], recorded: i64) -> Result<i64, SshScanErr> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO scans (recorded, args, nmap_version, start, end) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![recorded, info.args, info.nmap_version, info.start, info.end],
+        )?;
+        let scan_id = tx.last_insert_rowid();
+        for host in hosts {
+            tx.execute(
+                "INSERT INTO hosts (scan_id, addr, status) VALUES (?1, ?2, ?3)",
+                params![scan_id, host.addr, host.status.as_str()],
+            )?;
+            let host_id = tx.last_insert_rowid();
+            let mut insert = tx.prepare_cached("INSERT INTO host_names (host_id, name) VALUES (?1, ?2)")?;
+            for name in &host.hostnames {
+                insert.execute(params![host_id, name])?;
+            }
+            for hp in &host.port_states {
+                tx.execute(
+                    "INSERT INTO ports (host_id, port, open, product, hassh, sshv1) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![host_id, hp.portid, hp.state, hp.product, hp.hassh, hp.sshv1],
+                )?;
+                let port_id = tx.last_insert_rowid();
+                let mut insert = tx.prepare_cached("INSERT INTO algorithms (port_id, category, rank, name) VALUES (?1, ?2, ?3, ?4)")?;
+                for category in Algos::KEYS {
+                    for (rank, name) in hp.algos[category].iter().enumerate() {
+                        insert.execute(params![port_id, category, rank, name])?;
+                    }
+                }
+                let mut insert = tx.prepare_cached("INSERT INTO host_keys (port_id, type, bits, fingerprint, key) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+                for key in &hp.host_keys {
+                    insert.execute(params![port_id, key.key_type, key.bits, key.fingerprint, key.key])?;
+                }
+            }
+        }
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO findings (scan_id, addr, port, kind, severity, subject, message) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for f in findings {
+                insert.execute(params![scan_id, f.addr, f.portid, f.kind.id(), f.severity.as_str(), f.subject, f.message])?;
+            }
+        }
+        tx.commit()?;
+        Ok(scan_id)
+    }
+
+    /// List stored scans, oldest first
+    pub fn list(&self) -> Result<Vec<ScanRecord>, SshScanErr> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.recorded, s.args, s.nmap_version, s.start, s.end,
+                (SELECT count(*) FROM hosts h WHERE h.scan_id = s.id),
+                (SELECT count(*) FROM ports p JOIN hosts h ON h.id = p.host_id WHERE h.scan_id = s.id AND p.open),
+                (SELECT count(*) FROM findings f WHERE f.scan_id = s.id)
+             FROM scans s ORDER BY coalesce(s.start, s.recorded), s.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ScanRecord {
+                id: row.get(0)?,
+                recorded: row.get(1)?,
+                info: ScanInfo { args: row.get(2)?, nmap_version: row.get(3)?, start: row.get(4)?, end: row.get(5)? },
+                hosts: row.get(6)?,
+                open_ports: row.get(7)?,
+                findings: row.get(8)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Load a stored scan. Findings are not loaded, they are recomputed from the hosts.
+    pub fn load(&self, id: i64) -> Result<(ScanInfo, Vec<Host>), SshScanErr> {
+        let info = self
+            .conn
+            .query_row("SELECT args, nmap_version, start, end FROM scans WHERE id = ?1", [id], |row| {
+                Ok(ScanInfo { args: row.get(0)?, nmap_version: row.get(1)?, start: row.get(2)?, end: row.get(3)? })
+            })
+            .optional()?
+            .ok_or_else(|| SshScanErr::Storage(format!("no scan with id {id}")))?;
+        let mut host_stmt = self.conn.prepare("SELECT id, addr, status FROM hosts WHERE scan_id = ?1 ORDER BY id")?;
+        let mut port_stmt = self.conn.prepare("SELECT id, port, open, product, hassh, sshv1 FROM ports WHERE host_id = ?1 ORDER BY id")?;
+        let mut algo_stmt = self.conn.prepare("SELECT category, name FROM algorithms WHERE port_id = ?1 ORDER BY category, rank")?;
+        let mut name_stmt = self.conn.prepare("SELECT name FROM host_names WHERE host_id = ?1 ORDER BY rowid")?;
+        let mut key_stmt = self.conn.prepare("SELECT type, bits, fingerprint, key FROM host_keys WHERE port_id = ?1 ORDER BY rowid")?;
+        let host_rows = host_stmt
+            .query_map([id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut hosts = Vec::with_capacity(host_rows.len());
+        for (host_id, addr, status) in host_rows {
+            let port_rows = port_stmt
+                .query_map([host_id], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        Description {
+                            portid: row.get(1)?,
+                            state: row.get(2)?,
+                            product: row.get(3)?,
+                            hassh: row.get(4)?,
+                            sshv1: row.get(5)?,
+                            algos: Algos::default(),
+                            host_keys: Vec::new(),
+                        },
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut port_states = Vec::with_capacity(port_rows.len());
+            for (port_id, mut desc) in port_rows {
+                for algo in algo_stmt.query_map([port_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+                    let (category, name) = algo?;
+                    if Algos::KEYS.contains(&category.as_str()) {
+                        desc.algos[category.as_str()].push(name);
+                    }
+                }
+                desc.host_keys = key_stmt
+                    .query_map([port_id], |row| Ok(HostKey { key_type: row.get(0)?, bits: row.get(1)?, fingerprint: row.get(2)?, key: row.get(3)? }))?
+                    .collect::<Result<_, _>>()?;
+                port_states.push(desc);
+            }
+            let hostnames = name_stmt.query_map([host_id], |row| row.get(0))?.collect::<Result<_, _>>()?;
+            hosts.push(Host { status: HostStatus::from(status.as_str()), addr, hostnames, port_states });
+        }
+        Ok((info, hosts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{findings, fixtures};
+
+    #[test]
+    fn loads_what_was_saved() {
+        let mut port = fixtures::port(22, fixtures::algos([
+            "curve25519-sha256,diffie-hellman-group14-sha1",
+            "ssh-ed25519,rsa-sha2-512",
+            "aes128-ctr,aes256-gcm@openssh.com",
+            "hmac-sha1,hmac-sha2-256",
+            "none",
+        ]));
+        port.product = Some("OpenSSH 8.9p1".to_owned());
+        port.host_keys.push(HostKey::from_blob("ssh-ed25519", "AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl").unwrap());
+        let mut host = fixtures::host("10.0.0.1", vec![port]);
+        host.hostnames = vec!["bastion.example.com".to_owned(), "bastion".to_owned()];
+        let hosts = vec![host];
+        let info = ScanInfo { args: Some("nmap -p22 10.0.0.1".to_owned()), nmap_version: Some("7.94".to_owned()), start: Some(100), end: Some(110) };
+        let findings = findings::collect(&hosts);
+
+        let mut store = Store::open(std::path::Path::new(":memory:")).unwrap();
+        let id = store.save(&info, &hosts, &findings, 120).unwrap();
+        let (loaded_info, loaded) = store.load(id).unwrap();
+
+        assert_eq!(loaded_info.args, info.args);
+        assert_eq!(loaded_info.start, Some(100));
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].addr, "10.0.0.1");
+        assert_eq!(loaded[0].hostnames, hosts[0].hostnames);
+        let (saved, loaded) = (&hosts[0].port_states[0], &loaded[0].port_states[0]);
+        for category in Algos::KEYS {
+            assert_eq!(loaded.algos[category], saved.algos[category], "{category}");
+        }
+        assert_eq!(loaded.product, saved.product);
+        assert_eq!(loaded.hassh, saved.hassh);
+        assert_eq!(loaded.host_keys, saved.host_keys);
+
+        let records = store.list().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].id, records[0].hosts, records[0].open_ports), (id, 1, 1));
+        assert_eq!(records[0].findings, findings.len());
+        let stored: Vec<(String, String)> = store
+            .conn
+            .prepare("SELECT kind, subject FROM findings WHERE scan_id = ?1 ORDER BY rowid")
+            .unwrap()
+            .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected: Vec<(String, String)> = findings.iter().map(|f| (f.kind.id().to_owned(), f.subject.clone())).collect();
+        assert_eq!(stored, expected);
+    }
+}
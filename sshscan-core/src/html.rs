@@ -1,67 +1,236 @@
 use std::collections::HashMap;
 use build_html::{self, HtmlPage, Table, HtmlContainer, ContainerType, Container, Html, TableRow, TableCell, TableCellType};
-use crate::{agg_data::{self, AggregatedData}, Host};
+use crate::{agg_data::{self, AggregatedData, HostPort, Offer, Summary}, chart::{self, Bar, Series}, findings::Finding, negotiate::{ClientProfile, Negotiated, Selected}, strength::{self, Grade}, trend::{self, Trend}, anchor_id, host_port, Host, SshScanErr};
 
-struct HostHeader {
+pub(crate) struct HostHeader {
     pub title: &'static str,
     pub html_id: &'static str,
     pub data_key: &'static str,
+    pub algo_key: &'static str,
 }
 
 macro_rules! hh {
-    ($title:expr, $id:expr, $key:expr) => {
-        HostHeader {title: $title, html_id: $id, data_key: $key}
+    ($title:expr, $id:expr, $key:expr, $algo_key:expr) => {
+        HostHeader {title: $title, html_id: $id, data_key: $key, algo_key: $algo_key}
     };
 }
 
-const HOST_HEADERS: &[HostHeader; 5] = &[
-    hh!("Kex Algos", "sshscan-id-kex", "kex_algos"),
-    hh!("Host Key Algos", "sshscan-id-hkey", "host_key_algos"),
-    hh!("Encryption Algos", "sshscan-id-enc", "encryption_algos"),
-    hh!("MAC Algos", "sshscan-id-mac", "mac_algos"),
-    hh!("Compression Algos", "sshscan-id-compr", "compression_algos"),
+pub(crate) const HOST_HEADERS: &[HostHeader; 5] = &[
+    hh!("Kex Algos", "sshscan-id-kex", "kex_algos", "kex_algorithms"),
+    hh!("Host Key Algos", "sshscan-id-hkey", "host_key_algos", "server_host_key_algorithms"),
+    hh!("Encryption Algos", "sshscan-id-enc", "encryption_algos", "encryption_algorithms"),
+    hh!("MAC Algos", "sshscan-id-mac", "mac_algos", "mac_algorithms"),
+    hh!("Compression Algos", "sshscan-id-compr", "compression_algos", "compression_algorithms"),
 ];
-const LINK: &str = "https://github.com/AndrewPiroli/sshscan/";
-const NAME: &str = "sshscan";
-const STYLE: &str = include_str!("style.css");
-
-fn create_page() -> HtmlPage {
-    let time = {
-        use chrono::prelude::*;
-        let time: DateTime<Local> = Local::now();
-        time.format("%Y-%m-%dT%H.%M%z").to_string()
-    };
+/// Number of bars shown in summary charts with open-ended categories
+const CHART_TOP_N: usize = 10;
+pub(crate) const LINK: &str = "https://github.com/AndrewPiroli/sshscan/";
+pub(crate) const NAME: &str = "sshscan";
+pub(crate) const STYLE: &str = include_str!("style.css");
+const SCRIPT: &str = include_str!("report.js");
 
-    let page = HtmlPage::new()
-    .with_title("report")
-    .with_style(STYLE);
-    
-    page
+const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%dT%H.%M%z";
+
+/// Timezone used for timestamps in the report
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Timezone {
+    #[default]
+    Local,
+    Utc,
+    Fixed(chrono::FixedOffset),
+}
+
+impl std::str::FromStr for Timezone {
+    type Err = SshScanErr;
+
+    /// Accepts `local`, `utc` or a fixed offset such as `+02:00`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            Ok(Self::Local)
+        } else if s.eq_ignore_ascii_case("utc") || s == "Z" {
+            Ok(Self::Utc)
+        } else {
+            s.parse::<chrono::FixedOffset>()
+                .map(Self::Fixed)
+                .map_err(|_| SshScanErr::InvalidArgument(format!("invalid timezone '{s}', expected local, utc or an offset like +02:00")))
+        }
+    }
+}
+
+/// Presentation options for the HTML report
+#[derive(Debug, Clone, Default)]
+pub struct ReportOptions {
+    /// Replaces the default page title and main header
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    /// Additional CSS, applied after the built-in style
+    pub extra_css: Option<String>,
+    /// Image shown next to the main header, as a `data:` URI so the report stays self-contained
+    pub logo: Option<String>,
+    pub footer: Option<String>,
+    pub timezone: Timezone,
+    /// chrono `strftime` format for timestamps
+    pub time_format: Option<String>,
+}
+
+impl ReportOptions {
+    /// Check that `time_format` only contains valid specifiers, chrono panics on formatting otherwise.
+    /// # Errors
+    /// Returns error if the format string is invalid
+    pub fn validate(&self) -> Result<(), SshScanErr> {
+        use chrono::format::{Item, StrftimeItems};
+        if let Some(fmt) = &self.time_format && StrftimeItems::new(fmt).any(|item| matches!(item, Item::Error)) {
+            return Err(SshScanErr::InvalidArgument(format!("invalid time format '{fmt}'")));
+        }
+        Ok(())
+    }
+
+    /// Format a timestamp according to the configured timezone and format
+    #[must_use]
+    pub fn format_time(&self, time: chrono::DateTime<chrono::Utc>) -> String {
+        let fmt = self.time_format.as_deref().unwrap_or(DEFAULT_TIME_FORMAT);
+        match self.timezone {
+            Timezone::Local => time.with_timezone(&chrono::Local).format(fmt).to_string(),
+            Timezone::Utc => time.format(fmt).to_string(),
+            Timezone::Fixed(offset) => time.with_timezone(&offset).format(fmt).to_string(),
+        }
+    }
+
+    /// Format a Unix timestamp, `None` when it is out of range
+    #[must_use]
+    pub fn format_timestamp(&self, timestamp: i64) -> Option<String> {
+        chrono::DateTime::from_timestamp(timestamp, 0).map(|time| self.format_time(time))
+    }
+
+    #[must_use]
+    pub fn now(&self) -> String {
+        self.format_time(chrono::Utc::now())
+    }
+
+    #[must_use]
+    pub fn page_title(&self) -> &str {
+        self.title.as_deref().unwrap_or("report")
+    }
+}
+
+/// How [`generate_site`] splits hosts into pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SiteGrouping {
+    /// One page per host
+    #[default]
+    Host,
+    /// One page per IPv4 /24, other addresses get a page per host
+    Subnet,
+}
+
+impl std::str::FromStr for SiteGrouping {
+    type Err = SshScanErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "host" => Ok(Self::Host),
+            "subnet" => Ok(Self::Subnet),
+            _ => Err(SshScanErr::InvalidArgument(format!("invalid site grouping '{s}', expected host or subnet"))),
+        }
+    }
+}
+
+/// Name of the site page holding the host tables of `addr`
+#[must_use]
+pub fn site_page_name(addr: &str, grouping: SiteGrouping) -> String {
+    if grouping == SiteGrouping::Subnet && let Ok(v4) = addr.parse::<std::net::Ipv4Addr>() {
+        let [a, b, c, _] = v4.octets();
+        return format!("net-{a}.{b}.{c}.0_24.html");
+    }
+    // Keep file names portable, IPv6 colons are not allowed everywhere
+    let sanitized: String = addr.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' }).collect();
+    format!("host-{sanitized}.html")
+}
+
+/// Where the pieces of the report live, so links can point across pages
+#[derive(Debug, Clone, Copy)]
+enum Layout {
+    SinglePage,
+    Site(SiteGrouping),
+}
+
+impl Layout {
+    /// Page holding the summary and algorithm lists
+    const fn index(self) -> &'static str {
+        match self {
+            Self::SinglePage => "",
+            Self::Site(_) => "index.html",
+        }
+    }
+
+    /// Page holding the host table of `addr`
+    fn host_page(self, addr: &str) -> String {
+        match self {
+            Self::SinglePage => String::new(),
+            Self::Site(grouping) => site_page_name(addr, grouping),
+        }
+    }
+}
+
+fn create_page(options: &ReportOptions, layout: Layout) -> HtmlPage {
+    let time = options.now();
+
+    let mut page = HtmlPage::new()
+    .with_title(build_html::escape_html(options.page_title()));
+    match layout {
+        Layout::SinglePage => {
+            page.add_style(STYLE);
+            if let Some(css) = &options.extra_css {
+                page.add_style(css);
+            }
+            page.add_script_literal(SCRIPT);
+        },
+        Layout::Site(_) => {
+            page.add_stylesheet("style.css");
+            page.add_script_link("report.js");
+        },
+    }
+    let logo = options.logo.as_ref().map(|logo| format!("<img class=\"sshscan-logo\" src=\"{}\" alt=\"logo\"> ", build_html::escape_html(logo))).unwrap_or_default();
+    let title = options.title.as_ref().map_or_else(|| format!("<a href={LINK}>{NAME}</a> Report"), |t| build_html::escape_html(t));
+
+    page = page
     .with_meta([("name","date"), ("content", time.as_str())])
     .with_meta([("name","generator"), ("content", LINK)])
     .with_meta([("charset", "UTF-8")])
     .with_meta([("name","viewport"), ("content", "width=device-width, initial-scale=1.0, user-scalable=yes")])
-    .with_header(1, format!("<a href={LINK}>{NAME}</a> Report - Generated: {time}"))
-    .with_header(2, "Hosts")
+    .with_header(1, format!("{logo}{title} - Generated: {time}"));
+    if let Some(subtitle) = &options.subtitle {
+        page.add_paragraph_attr(build_html::escape_html(subtitle), [("class", "sshscan-subtitle")]);
+    }
+    page
+}
+
+fn add_footer(page: &mut HtmlPage, options: &ReportOptions) {
+    if let Some(footer) = &options.footer {
+        page.add_container(Container::new(ContainerType::Footer)
+        .with_attributes([("class", "sshscan-footer")])
+        .with_paragraph(build_html::escape_html(footer)));
+    }
 }
 
-fn build_host_table(rows: &[Vec<String>]) -> Table {
+fn build_host_table(rows: &[Vec<String>], layout: Layout) -> Table {
+    let index = layout.index();
     let mut tab = Table::new().with_attributes([("class", "sshscan-table")]);
     let header_row = {
-        let mut header_row = TableRow::new();
+        let mut header_row = TableRow::new().with_cell(TableCell::new(TableCellType::Header).with_raw("#"));
         for header in HOST_HEADERS {
-            header_row.add_cell(TableCell::new(TableCellType::Header).with_link(format!("#{}", header.html_id), header.title));
+            header_row.add_cell(TableCell::new(TableCellType::Header).with_link(format!("{index}#{}", header.html_id), header.title));
         }
         header_row
     };
     tab.add_custom_header_row(header_row);
-    for row in rows {
-        let mut r = TableRow::new();
+    for (rank, row) in rows.iter().enumerate() {
+        let mut r = TableRow::new().with_cell(TableCell::new(TableCellType::Data).with_raw(rank + 1));
         for entry in row {
             if entry.is_empty() {
                 r.add_cell(TableCell::new(TableCellType::Data));
             } else {
-                r.add_cell(TableCell::new(TableCellType::Data).with_link(format!("#algo-{entry}"), entry));
+                r.add_cell(TableCell::new(TableCellType::Data).with_link(format!("{index}#algo-{entry}"), entry));
             }
         }
         tab.add_custom_body_row(r);
@@ -69,48 +238,407 @@ fn build_host_table(rows: &[Vec<String>]) -> Table {
     tab
 }
 
-fn create_host_table(host: &Host) -> Container {
+fn build_compat_table(negotiations: &[Negotiated], layout: Layout) -> Table {
+    let index = layout.index();
+    let mut tab = Table::new().with_attributes([("class", "sshscan-table sshscan-compat")]);
+    let mut header_row = TableRow::new().with_cell(TableCell::new(TableCellType::Header).with_raw("Client"));
+    for header in HOST_HEADERS {
+        header_row.add_cell(TableCell::new(TableCellType::Header).with_raw(header.title));
+    }
+    tab.add_custom_header_row(header_row);
+    for negotiated in negotiations {
+        let mut r = TableRow::new().with_cell(TableCell::new(TableCellType::Data).with_raw(build_html::escape_html(&negotiated.profile)));
+        for selected in &negotiated.selected {
+            match selected {
+                Selected::Algorithm(algo) => r.add_cell(TableCell::new(TableCellType::Data).with_link(format!("{index}#algo-{algo}"), algo)),
+                Selected::Implicit => r.add_cell(TableCell::new(TableCellType::Data).with_raw("Implicit (AEAD cipher)")),
+                Selected::NoCommon => r.add_cell(TableCell::new(TableCellType::Data).with_attributes([("class", "sshscan-fail")]).with_raw("No common algorithm")),
+            }
+        }
+        tab.add_custom_body_row(r);
+    }
+    tab
+}
+
+fn create_host_table(host: &Host, profiles: &[ClientProfile], layout: Layout) -> Container {
+    let index = layout.index();
     let mut c = Container::new(ContainerType::Div).with_attributes([("class", "sshscan-htable-outer")]);
-    let data = agg_data::wrangle_host_to_table(host);
+    let data = agg_data::wrangle_host_to_table(host, profiles);
     for t in data {
-        let id = format!("{}:{}", host.addr, t.port);
-        let mut inner = Container::new(ContainerType::Div).with_attributes([("class", "sshscan-htable-inner"), ("id", id.as_str())]);
-        inner.add_header(3, format!("{} {}", id, t.product.unwrap_or_default()));
-        let tab = build_host_table(&t.algos);
+        let (name, id) = (host_port(&host.addr, t.port), anchor_id(&host.addr, t.port));
+        let port = t.port.to_string();
+        let product = t.product.unwrap_or_default();
+        let escaped_product = build_html::escape_html(&product);
+        let mut algos: Vec<&str> = t.algos.iter().flatten().filter(|a| !a.is_empty()).map(String::as_str).collect();
+        algos.sort_unstable();
+        algos.dedup();
+        let algos = algos.join(" ");
+        let mut inner = Container::new(ContainerType::Div).with_attributes([
+            ("class", "sshscan-htable-inner"),
+            ("id", id.as_str()),
+            ("data-addr", host.addr.as_str()),
+            ("data-port", port.as_str()),
+            ("data-product", escaped_product.as_str()),
+            ("data-algos", algos.as_str()),
+        ]);
+        match t.grade {
+            Some(grade) => inner.add_header(3, format!("{name} {escaped_product} <span class=\"sshscan-grade sshscan-grade-{grade}\">Grade: {grade}</span>")),
+            None => inner.add_header(3, format!("{name} {escaped_product}")),
+        }
+        if let Some(hassh) = &t.hassh {
+            inner.add_paragraph(format!("HASSH: <a href=\"{index}#hassh-{hassh}\">{hassh}</a>"));
+        }
+        if !t.warnings.is_empty() {
+            let mut warnings = Container::new(ContainerType::UnorderedList).with_attributes([("class", "sshscan-warning")]);
+            for warning in &t.warnings {
+                warnings.add_html(format!("Weak preference: {warning}"));
+            }
+            inner.add_container(warnings);
+        }
+        let tab = build_host_table(&t.algos, layout);
         inner.add_table(tab);
+        if !t.negotiations.is_empty() {
+            inner.add_table(build_compat_table(&t.negotiations, layout));
+        }
         c.add_container(inner);
     }
     c
 }
 
-pub fn generate(hosts: &[Host], agg_data: &AggregatedData) -> String {
-    if hosts.is_empty() { return create_page().to_html_string(); }
-    let mut page = create_page();
-    for host_table in hosts.iter().map(create_host_table) {
-        page.add_container(host_table);
+fn create_host_section<'h>(hosts: impl Iterator<Item = &'h Host>, profiles: &[ClientProfile], layout: Layout) -> Container {
+    let mut host_section = Container::new(ContainerType::Div)
+    .with_attributes([("id", "sshscan-hosts")])
+    .with_header(2, "Hosts");
+    for host_table in hosts.map(|host| create_host_table(host, profiles, layout)) {
+        host_section.add_container(host_table);
+    }
+    host_section
+}
+
+fn add_algo_lists(page: &mut HtmlPage, agg_data: &AggregatedData, layout: Layout) {
+    for header in HOST_HEADERS {
+        page.add_container(create_algo_list(header.title, header.html_id, &agg_data[header.data_key], layout));
+    }
+    page.add_container(create_hassh_list(agg_data.hassh(), layout));
+}
+
+/// Generate the single page report. `key_changes` are shown in a highlighted section at the top.
+pub fn generate(hosts: &[Host], agg_data: &AggregatedData, profiles: &[ClientProfile], key_changes: &[Finding], options: &ReportOptions) -> String {
+    let layout = Layout::SinglePage;
+    let mut page = create_page(options, layout);
+    if hosts.is_empty() {
+        add_footer(&mut page, options);
+        return page.to_html_string();
     }
+    if !key_changes.is_empty() {
+        page.add_container(create_key_changes(key_changes, layout));
+    }
+    page.add_container(create_summary(hosts, agg_data));
+    page.add_container(create_host_section(hosts.iter(), profiles, layout));
+    add_algo_lists(&mut page, agg_data, layout);
+    add_footer(&mut page, options);
+    page.to_html_string()
+}
+
+fn text_cell(text: &str) -> TableCell {
+    TableCell::new(TableCellType::Data).with_raw(build_html::escape_html(text))
+}
+
+fn header_row<'a>(titles: impl IntoIterator<Item = &'a str>) -> TableRow {
+    let mut row = TableRow::new();
+    for title in titles {
+        row.add_cell(TableCell::new(TableCellType::Header).with_raw(build_html::escape_html(title)));
+    }
+    row
+}
+
+/// Generate a trend report comparing scans: grade distribution and algorithm prevalence over time,
+/// grade changes between consecutive scans and time to remediate findings.
+#[must_use]
+pub fn generate_trend(trend: &Trend, options: &ReportOptions) -> String {
+    let mut page = create_page(options, Layout::SinglePage);
+    let labels: Vec<String> = (0..trend.scans.len()).map(|idx| trend.scan_label(idx, options)).collect();
+
+    let mut scans = Table::new().with_attributes([("class", "sshscan-table")]);
+    scans.add_custom_header_row(header_row(["Scan", "Open ports"].into_iter().chain(Grade::ALL.iter().map(|g| g.as_str()))));
+    for (label, scan) in labels.iter().zip(&trend.scans) {
+        let mut row = TableRow::new().with_cell(text_cell(label)).with_cell(text_cell(&scan.ports_open.to_string()));
+        for count in scan.grades {
+            row.add_cell(text_cell(&count.to_string()));
+        }
+        scans.add_custom_body_row(row);
+    }
+    let series: Vec<Series> = Grade::ALL
+        .iter()
+        .enumerate()
+        .map(|(idx, g)| Series { label: format!("Grade {g}"), values: trend.scans.iter().map(|s| s.grades[idx]).collect(), color: chart::grade_color(*g) })
+        .collect();
+    page.add_container(
+        Container::new(ContainerType::Div)
+        .with_attributes([("id", "sshscan-summary")])
+        .with_header(2, "Grades over time")
+        .with_html(chart::line_chart("Grade distribution", &labels, &series))
+        .with_table(scans),
+    );
+
+    let mut prevalence = Container::new(ContainerType::Div).with_attributes([("id", "sshscan-trend-prevalence")]).with_header(2, "Algorithm prevalence over time");
     for header in HOST_HEADERS {
-        page.add_container(create_algo_list(header.title, header.html_id, &agg_data[header.data_key]));
+        let mut tab = Table::new().with_attributes([("class", "sshscan-table")]);
+        tab.add_custom_header_row(header_row(["Algorithm", "Rating"].into_iter().chain(labels.iter().map(String::as_str))));
+        for p in trend.prevalence.iter().filter(|p| p.category == header.title) {
+            let mut row = TableRow::new().with_cell(text_cell(&p.algorithm)).with_cell(text_cell(p.strength.as_str()));
+            for count in &p.counts {
+                row.add_cell(text_cell(&count.to_string()));
+            }
+            tab.add_custom_body_row(row);
+        }
+        prevalence.add_header(3, header.title);
+        prevalence.add_table(tab);
+    }
+    page.add_container(prevalence);
+
+    let mut changes = Container::new(ContainerType::Div).with_attributes([("id", "sshscan-trend-changes")]).with_header(2, "Changes between scans");
+    for transition in &trend.transitions {
+        changes.add_header(3, build_html::escape_html(&format!("{} to {}", labels[transition.scan - 1], labels[transition.scan])));
+        if transition.is_empty() {
+            changes.add_paragraph("No grade changes");
+            continue;
+        }
+        let mut tab = Table::new().with_attributes([("class", "sshscan-table")]);
+        tab.add_custom_header_row(header_row(["Change", "Host:port", "Before", "After"]));
+        for (what, list) in [("Fixed", &transition.fixed), ("Regressed", &transition.regressed)] {
+            for change in list {
+                tab.add_custom_body_row(
+                    TableRow::new()
+                    .with_cell(text_cell(what))
+                    .with_cell(text_cell(&change.host_port))
+                    .with_cell(text_cell(change.before.as_str()))
+                    .with_cell(text_cell(change.after.as_str())),
+                );
+            }
+        }
+        for (what, list) in [("Appeared", &transition.appeared), ("Disappeared", &transition.disappeared)] {
+            for host_port in list {
+                tab.add_custom_body_row(TableRow::new().with_cell(text_cell(what)).with_cell(text_cell(host_port)).with_cell(text_cell("")).with_cell(text_cell("")));
+            }
+        }
+        changes.add_table(tab);
+    }
+    page.add_container(changes);
+
+    let mut mttr = Table::new().with_attributes([("class", "sshscan-table")]);
+    mttr.add_custom_header_row(header_row(["Finding", "Fixed", "Mean time to remediate"]));
+    for (kind, seconds, count) in trend.mean_time_to_remediate() {
+        mttr.add_custom_body_row(TableRow::new().with_cell(text_cell(kind.description())).with_cell(text_cell(&count.to_string())).with_cell(text_cell(&trend::format_duration(seconds))));
     }
+    let mut findings = Table::new().with_attributes([("class", "sshscan-table")]);
+    findings.add_custom_header_row(header_row(["Host:port", "Finding", "Subject", "First seen", "Fixed", "Time to remediate"]));
+    for r in &trend.remediations {
+        findings.add_custom_body_row(
+            TableRow::new()
+            .with_cell(text_cell(&r.host_port))
+            .with_cell(text_cell(r.kind.id()))
+            .with_cell(text_cell(&r.subject))
+            .with_cell(text_cell(&labels[r.first_seen]))
+            .with_cell(r.fixed.map_or_else(|| TableCell::new(TableCellType::Data).with_attributes([("class", "sshscan-fail")]).with_raw("Open"), |idx| text_cell(&labels[idx])))
+            .with_cell(text_cell(&r.duration(&trend.scans).map(trend::format_duration).unwrap_or_default())),
+        );
+    }
+    page.add_container(
+        Container::new(ContainerType::Div)
+        .with_attributes([("id", "sshscan-trend-remediation")])
+        .with_header(2, "Time to remediate")
+        .with_table(mttr)
+        .with_table(findings),
+    );
+    add_footer(&mut page, options);
     page.to_html_string()
 }
 
-fn create_algo_list(title: &str, title_id: &str, list: &HashMap<String, Vec<&Host>>) -> Container {
+/// Generate the report as a static site: `index.html` with the summary, host, product and
+/// algorithm indexes, one page per host (or subnet) and shared `style.css` and `report.js`.
+///
+/// Returns `(file name, contents)` pairs, all file names are relative to the same directory.
+#[must_use]
+pub fn generate_site(hosts: &[Host], agg_data: &AggregatedData, profiles: &[ClientProfile], key_changes: &[Finding], options: &ReportOptions, grouping: SiteGrouping) -> Vec<(String, String)> {
+    let layout = Layout::Site(grouping);
+    let mut files = Vec::new();
+
+    let mut style = STYLE.to_owned();
+    if let Some(css) = &options.extra_css {
+        style.push('\n');
+        style.push_str(css);
+    }
+    files.push(("style.css".to_owned(), style));
+    files.push(("report.js".to_owned(), SCRIPT.to_owned()));
+
+    // Group hosts by page, keeping scan order
+    let mut pages: Vec<(String, Vec<&Host>)> = Vec::new();
+    let mut page_idx: HashMap<String, usize> = HashMap::new();
+    for host in hosts {
+        let name = layout.host_page(&host.addr);
+        match page_idx.get(&name) {
+            Some(&idx) => pages[idx].1.push(host),
+            None => {
+                page_idx.insert(name.clone(), pages.len());
+                pages.push((name, vec![host]));
+            },
+        }
+    }
+
+    let mut index = create_page(options, layout);
+    if !hosts.is_empty() {
+        if !key_changes.is_empty() {
+            index.add_container(create_key_changes(key_changes, layout));
+        }
+        index.add_container(create_summary(hosts, agg_data));
+        index.add_container(create_host_index(hosts, layout));
+        index.add_container(create_product_index(hosts, layout));
+        add_algo_lists(&mut index, agg_data, layout);
+    }
+    add_footer(&mut index, options);
+    files.push(("index.html".to_owned(), index.to_html_string()));
+
+    for (name, page_hosts) in pages {
+        let mut page = create_page(options, layout);
+        page.add_paragraph(format!("<a href=\"{}\">Back to index</a>", layout.index()));
+        page.add_container(create_host_section(page_hosts.into_iter(), profiles, layout));
+        add_footer(&mut page, options);
+        files.push((name, page.to_html_string()));
+    }
+    files
+}
+
+/// Host key changes, a possible MITM, go in a red section above everything else
+fn create_key_changes(key_changes: &[Finding], layout: Layout) -> Container {
+    let mut list = Container::new(ContainerType::UnorderedList);
+    for change in key_changes {
+        let (name, id) = (host_port(&change.addr, change.portid), anchor_id(&change.addr, change.portid));
+        list.add_html(format!(
+            "<a href=\"{}#{id}\">{name}</a>: {}",
+            layout.host_page(&change.addr),
+            build_html::escape_html(&change.message),
+        ));
+    }
+    Container::new(ContainerType::Div)
+    .with_attributes([("id", "sshscan-key-changes"), ("class", "sshscan-alert")])
+    .with_header(2, "Host key changes")
+    .with_paragraph("The host keys below differ from the expected ones. The machine may have been reinstalled, or connections to it intercepted.")
+    .with_container(list)
+}
+
+fn create_host_index(hosts: &[Host], layout: Layout) -> Container {
+    let mut list = Container::new(ContainerType::UnorderedList);
+    for host in hosts {
+        let page = layout.host_page(&host.addr);
+        for host_port in &host.port_states {
+            let (name, id) = (crate::host_port(&host.addr, host_port.portid), anchor_id(&host.addr, host_port.portid));
+            let grade = strength::grade(&host_port.algos)
+                .map(|g| format!(" <span class=\"sshscan-grade sshscan-grade-{g}\">Grade: {g}</span>"))
+                .unwrap_or_default();
+            let product = build_html::escape_html(host_port.product.as_deref().unwrap_or_default());
+            list.add_html(format!("<a href=\"{page}#{id}\">{name}</a> {product}{grade}"));
+        }
+    }
+    Container::new(ContainerType::Div)
+    .with_attributes([("class", "sshscan-alist-outer")])
+    .with_header_attr(2, "Hosts", [("id", "sshscan-id-hosts")])
+    .with_container(list)
+}
+
+fn create_product_index(hosts: &[Host], layout: Layout) -> Container {
+    let mut by_product: HashMap<&str, Vec<(&Host, u16)>> = HashMap::new();
+    for host in hosts {
+        for host_port in &host.port_states {
+            let product = host_port.product.as_deref().unwrap_or("Unknown");
+            by_product.entry(product).or_default().push((host, host_port.portid));
+        }
+    }
+    let mut products: Vec<(&str, Vec<(&Host, u16)>)> = by_product.into_iter().collect();
+    products.sort_unstable_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+    let mut c = Container::new(ContainerType::Div)
+    .with_attributes([("class", "sshscan-alist-outer")])
+    .with_header_attr(2, "Products", [("id", "sshscan-id-products")]);
+    for (product, host_ports) in products {
+        let mut inner = Container::new(ContainerType::UnorderedList);
+        for (host, portid) in &host_ports {
+            let id = anchor_id(&host.addr, *portid);
+            inner.add_link(format!("{}#{id}", layout.host_page(&host.addr)), host_port(&host.addr, *portid));
+        }
+        let count = host_ports.len().to_string();
+        let product = build_html::escape_html(product);
+        c.add_container(Container::new(ContainerType::Div)
+        .with_attributes([("class", "sshscan-alist-inner"), ("data-name", product.as_str()), ("data-count", count.as_str())])
+        .with_header(3, format!("{product} <span class=\"header-count\">Count: {count}</span>"))
+        .with_container(inner));
+    }
+    c
+}
+
+fn create_algo_list(title: &str, title_id: &str, list: &HashMap<String, Vec<Offer<'_>>>, layout: Layout) -> Container {
     let mut c = Container::new(ContainerType::Div)
     .with_attributes([("class", "sshscan-alist-outer")])
     .with_header_attr(2, title, [("id", title_id)]);
-    for algo in list {
+    for (algo, offers) in list {
         let mut inner = Container::new(ContainerType::UnorderedList);
-        for host in algo.1 {
-            for host_port in &host.port_states {
-                let id = format!("{}:{}", host.addr, host_port.portid);
-                inner.add_link(format!("#{id}"), id.as_str());
-            }
+        for offer in offers {
+            let (name, id) = (host_port(&offer.host.addr, offer.portid), anchor_id(&offer.host.addr, offer.portid));
+            inner.add_html(format!("<a href=\"{}#{id}\">{name}</a> <span class=\"header-count\">Preference: {}</span>", layout.host_page(&offer.host.addr), offer.rank + 1));
+        }
+        let count = offers.len().to_string();
+        let first = agg_data::first_choice_count(offers).to_string();
+        c.add_container(Container::new(ContainerType::Div)
+        .with_attributes([("class", "sshscan-alist-inner"), ("data-name", algo.as_str()), ("data-count", count.as_str()), ("data-first", first.as_str())])
+        .with_header_attr(3, format!("{algo} <span class=\"header-count\">Count: {count} First choice: {first}</span>"), [("id", format!("algo-{algo}").as_str())])
+        .with_container(inner));
+    }
+    c
+}
+
+fn create_hassh_list(list: &HashMap<String, Vec<HostPort<'_>>>, layout: Layout) -> Container {
+    let mut c = Container::new(ContainerType::Div)
+    .with_attributes([("class", "sshscan-alist-outer")])
+    .with_header_attr(2, "HASSH Fingerprints", [("id", "sshscan-id-hassh")]);
+    for (hassh, host_ports) in list {
+        let mut inner = Container::new(ContainerType::UnorderedList);
+        for hp in host_ports {
+            let id = anchor_id(&hp.host.addr, hp.portid);
+            inner.add_link(format!("{}#{id}", layout.host_page(&hp.host.addr)), host_port(&hp.host.addr, hp.portid));
         }
+        let count = host_ports.len().to_string();
         c.add_container(Container::new(ContainerType::Div)
-        .with_attributes([("class", "sshscan-alist-inner")])
-        .with_header_attr(3, format!("{} <span class=\"header-count\">Count: {}</span>", algo.0, algo.1.len()), [("id", format!("algo-{}", algo.0).as_str())])
+        .with_attributes([("class", "sshscan-alist-inner"), ("data-name", hassh.as_str()), ("data-count", count.as_str())])
+        .with_header_attr(3, format!("{hassh} <span class=\"header-count\">Count: {count}</span>"), [("id", format!("hassh-{hassh}").as_str())])
         .with_container(inner));
     }
     c
-}
\ No newline at end of file
+}
+
+fn top_n(counts: Vec<(&str, usize)>, color: impl Fn(&str) -> &'static str) -> Vec<Bar> {
+    counts.into_iter().take(CHART_TOP_N).map(|(label, value)| Bar { label: label.to_owned(), value, color: color(label) }).collect()
+}
+
+fn create_summary(hosts: &[Host], agg_data: &AggregatedData) -> Container {
+    let summary = Summary::build_from_hosts(hosts);
+    let mut charts = Container::new(ContainerType::Div).with_attributes([("class", "sshscan-charts")]);
+    let grade_bars: Vec<Bar> = Grade::ALL
+        .iter()
+        .zip(summary.grades)
+        .map(|(g, value)| Bar { label: format!("Grade {g}"), value, color: chart::grade_color(*g) })
+        .collect();
+    charts.add_html(chart::bar_chart("Grade distribution", &grade_bars));
+    charts.add_html(chart::bar_chart("Port states", &[
+        Bar { label: "Open".to_owned(), value: summary.ports_open, color: chart::grade_color(Grade::A) },
+        Bar { label: "Closed / filtered".to_owned(), value: summary.ports_closed, color: chart::grade_color(Grade::F) },
+    ]));
+    charts.add_html(chart::bar_chart("Software versions", &top_n(summary.products_by_count(), |_| "#455a64")));
+    for header in HOST_HEADERS {
+        let counts = agg_data::by_count(&agg_data[header.data_key]).into_iter().map(|(algo, offers)| (algo.as_str(), offers.len())).collect();
+        let bars = top_n(counts, |algo| chart::strength_color(strength::classify(header.algo_key, algo)));
+        charts.add_html(chart::bar_chart(&format!("{} prevalence", header.title), &bars));
+    }
+    Container::new(ContainerType::Div)
+    .with_attributes([("id", "sshscan-summary")])
+    .with_header(2, "Summary")
+    .with_container(charts)
+}
@@ -0,0 +1,152 @@
+use serde_json::{Map, Value, json};
+use crate::{
+    findings::Finding,
+    strength, Algos, Description, Host, HostKey, HostStatus, ScanInfo, SshScanErr,
+};
+
+fn algos_value(algos: &Algos) -> Value {
+    Value::Object(Algos::KEYS.iter().map(|key| ((*key).to_owned(), json!(algos[key]))).collect())
+}
+
+fn host_value(host: &Host) -> Value {
+    let ports: Vec<Value> = host
+        .port_states
+        .iter()
+        .map(|hp| json!({
+            "port": hp.portid,
+            "open": hp.state,
+            "product": hp.product,
+            "hassh": hp.hassh,
+            "sshv1": hp.sshv1,
+            "grade": strength::grade(&hp.algos).map(|g| g.as_str()),
+            "algorithms": algos_value(&hp.algos),
+            "host_keys": hp.host_keys.iter().map(|k| json!({
+                "type": k.key_type,
+                "bits": k.bits,
+                "fingerprint": k.fingerprint,
+                "key": k.key,
+            })).collect::<Vec<_>>(),
+        }))
+        .collect();
+    json!({ "addr": host.addr, "hostnames": host.hostnames, "status": host.status.as_str(), "ports": ports })
+}
+
+fn finding_value(finding: &Finding) -> Value {
+    json!({
+        "addr": finding.addr,
+        "port": finding.portid,
+        "kind": finding.kind.id(),
+        "severity": finding.severity.as_str(),
+        "subject": finding.subject,
+        "message": finding.message,
+    })
+}
+
+/// Build the JSON document for a scan: metadata, hosts with their algorithms, and findings
+#[must_use]
+pub fn to_value(hosts: &[Host], info: &ScanInfo, findings: &[Finding]) -> Value {
+    json!({
+        "scan": {
+            "args": info.args,
+            "nmap_version": info.nmap_version,
+            "start": info.start,
+            "end": info.end,
+        },
+        "hosts": hosts.iter().map(host_value).collect::<Vec<_>>(),
+        "findings": findings.iter().map(finding_value).collect::<Vec<_>>(),
+    })
+}
+
+/// Generate a JSON report, readable again with [`parse`]
+#[must_use]
+pub fn generate(hosts: &[Host], info: &ScanInfo, findings: &[Finding]) -> String {
+    serde_json::to_string_pretty(&to_value(hosts, info, findings)).unwrap_or_default()
+}
+
+fn opt_string(obj: &Map<String, Value>, key: &str) -> Option<String> {
+    obj.get(key).and_then(Value::as_str).map(ToOwned::to_owned)
+}
+
+fn parse_port(value: &Value) -> Result<Description, SshScanErr> {
+    let obj = value.as_object().ok_or(SshScanErr::JSONInvalid)?;
+    let portid = obj
+        .get("port")
+        .and_then(Value::as_u64)
+        .and_then(|p| u16::try_from(p).ok())
+        .ok_or(SshScanErr::JSONInvalid)?;
+    let mut algos = Algos::default();
+    if let Some(list) = obj.get("algorithms").and_then(Value::as_object) {
+        for key in Algos::KEYS {
+            algos[key] = list
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(Value::as_str).map(ToOwned::to_owned).collect())
+                .unwrap_or_default();
+        }
+    }
+    let host_keys = obj
+        .get("host_keys")
+        .and_then(Value::as_array)
+        .map(|keys| {
+            keys.iter()
+                .filter_map(Value::as_object)
+                .map(|k| HostKey {
+                    key_type: opt_string(k, "type").unwrap_or_default(),
+                    bits: k.get("bits").and_then(Value::as_u64).and_then(|b| u32::try_from(b).ok()),
+                    fingerprint: opt_string(k, "fingerprint").unwrap_or_default(),
+                    key: opt_string(k, "key"),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Description {
+        portid,
+        state: obj.get("open").and_then(Value::as_bool).unwrap_or(false),
+        product: opt_string(obj, "product"),
+        hassh: opt_string(obj, "hassh"),
+        sshv1: obj.get("sshv1").and_then(Value::as_bool).unwrap_or(false),
+        algos,
+        host_keys,
+    })
+}
+
+fn parse_host(value: &Value) -> Result<Host, SshScanErr> {
+    let obj = value.as_object().ok_or(SshScanErr::JSONInvalid)?;
+    let port_states = obj
+        .get("ports")
+        .and_then(Value::as_array)
+        .map(|ports| ports.iter().map(parse_port).collect::<Result<Vec<_>, _>>())
+        .transpose()?
+        .unwrap_or_default();
+    Ok(Host {
+        status: obj.get("status").and_then(Value::as_str).map(HostStatus::from).unwrap_or_default(),
+        addr: opt_string(obj, "addr").ok_or(SshScanErr::JSONInvalid)?,
+        hostnames: obj
+            .get("hostnames")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).map(ToOwned::to_owned).collect())
+            .unwrap_or_default(),
+        port_states,
+    })
+}
+
+/// Read scan metadata and hosts back from a JSON document made by [`generate`].
+/// Findings are not read, they are recomputed from the hosts when needed.
+pub fn parse(data: &str) -> Result<(ScanInfo, Vec<Host>), SshScanErr> {
+    let doc: Value = serde_json::from_str(data)?;
+    let scan = doc.get("scan").and_then(Value::as_object);
+    let info = ScanInfo {
+        args: scan.and_then(|s| opt_string(s, "args")),
+        nmap_version: scan.and_then(|s| opt_string(s, "nmap_version")),
+        start: scan.and_then(|s| s.get("start")).and_then(Value::as_i64),
+        end: scan.and_then(|s| s.get("end")).and_then(Value::as_i64),
+    };
+    let hosts = doc
+        .get("hosts")
+        .and_then(Value::as_array)
+        .ok_or(SshScanErr::JSONInvalid)?
+        .iter()
+        .map(parse_host)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((info, hosts))
+}
@@ -0,0 +1,84 @@
+use std::fmt::Write;
+use crate::{findings::Finding, Host};
+
+/// Escape text for XML attribute values and character data
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Generate a JUnit XML report with one testsuite per host and one testcase per host:port.
+/// Each policy violation (warning or error) is a failure of the testcase, notes are attached
+/// as output and closed ports are skipped.
+#[must_use]
+pub fn generate(hosts: &[Host], findings: &[Finding]) -> String {
+    let mut suites = String::new();
+    let (mut total_tests, mut total_failures, mut total_skipped) = (0, 0, 0);
+    for host in hosts {
+        let (mut failures, mut skipped) = (0, 0);
+        let mut cases = String::new();
+        for host_port in &host.port_states {
+            let id = crate::host_port(&host.addr, host_port.portid);
+            let _ = write!(cases, "    <testcase classname=\"sshscan.{}\" name=\"{}\"", escape(&host.addr), escape(&id));
+            if !host_port.state {
+                skipped += 1;
+                cases.push_str(">\n      <skipped message=\"port not open\"/>\n    </testcase>\n");
+                continue;
+            }
+            let (violations, notes): (Vec<&Finding>, Vec<&Finding>) = findings
+                .iter()
+                .filter(|f| f.addr == host.addr && f.portid == host_port.portid)
+                .partition(|f| f.is_violation());
+            if violations.is_empty() && notes.is_empty() {
+                cases.push_str("/>\n");
+                continue;
+            }
+            cases.push_str(">\n");
+            if !violations.is_empty() {
+                failures += 1;
+            }
+            for violation in &violations {
+                let _ = writeln!(
+                    cases,
+                    "      <failure type=\"{}\" message=\"{}\">[{}] {}</failure>",
+                    violation.kind,
+                    escape(&violation.message),
+                    violation.severity,
+                    escape(&violation.subject),
+                );
+            }
+            if !notes.is_empty() {
+                cases.push_str("      <system-out>");
+                for note in &notes {
+                    let _ = writeln!(cases, "[{}] {}: {}", note.severity, note.kind, escape(&note.message));
+                }
+                cases.push_str("</system-out>\n");
+            }
+            cases.push_str("    </testcase>\n");
+        }
+        let tests = host.port_states.len();
+        let _ = write!(
+            suites,
+            "  <testsuite name=\"{}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"0\" skipped=\"{skipped}\">\n{cases}  </testsuite>\n",
+            escape(&host.addr),
+        );
+        total_tests += tests;
+        total_failures += failures;
+        total_skipped += skipped;
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"sshscan\" tests=\"{total_tests}\" failures=\"{total_failures}\" errors=\"0\" skipped=\"{total_skipped}\">\n{suites}</testsuites>\n"
+    )
+}
@@ -0,0 +1,353 @@
+use std::fmt::Write;
+use sha1::{Digest, Sha1};
+use crate::{baseline, findings::{Finding, FindingKind}, Host, HostKey, SshScanErr};
+
+const DEFAULT_PORT: u16 = 22;
+
+/// A host name or address with the port it applies to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HostName {
+    pub name: String,
+    pub port: u16,
+}
+
+impl HostName {
+    /// The name as written in `known_hosts`: `host` for port 22, `[host]:port` otherwise
+    #[must_use]
+    pub fn to_known_hosts(&self) -> String {
+        if self.port == DEFAULT_PORT {
+            self.name.clone()
+        } else {
+            format!("[{}]:{}", self.name, self.port)
+        }
+    }
+}
+
+/// Marker at the start of a `known_hosts` line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    /// The key is a certificate authority trusted to sign host certificates, not a host key
+    CertAuthority,
+    /// The key must never be accepted
+    Revoked,
+}
+
+#[derive(Debug, Clone)]
+pub enum HostPattern {
+    /// Host name, address or wildcard pattern (`*`, `?`), optionally negated with `!`
+    Name { pattern: HostName, negated: bool },
+    /// `|1|salt|hash`, the HMAC-SHA1 of the host name keyed with the salt
+    Hashed { salt: Vec<u8>, hash: [u8; 20] },
+}
+
+impl HostPattern {
+    fn matches(&self, name: &HostName) -> bool {
+        match self {
+            Self::Name { pattern, .. } => pattern.port == name.port && glob_match(&pattern.name.to_ascii_lowercase(), &name.name.to_ascii_lowercase()),
+            Self::Hashed { salt, hash } => hmac_sha1(salt, name.to_known_hosts().as_bytes()) == *hash,
+        }
+    }
+}
+
+/// One key line of a `known_hosts` file
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub marker: Option<Marker>,
+    pub patterns: Vec<HostPattern>,
+    pub key: HostKey,
+}
+
+impl Entry {
+    /// Whether the entry applies to a host known by any of `names` on `port`. As in OpenSSH,
+    /// a matching negated pattern excludes the host even when another pattern matches.
+    #[must_use]
+    pub fn matches(&self, names: &[&str], port: u16) -> bool {
+        let mut matched = false;
+        for name in names {
+            let name = HostName { name: (*name).to_owned(), port };
+            for pattern in &self.patterns {
+                if pattern.matches(&name) {
+                    if matches!(pattern, HostPattern::Name { negated: true, .. }) {
+                        return false;
+                    }
+                    matched = true;
+                }
+            }
+        }
+        matched
+    }
+}
+
+/// HMAC-SHA1 as used for hashed host names (RFC 2104)
+fn hmac_sha1(key: &[u8], data: &[u8]) -> [u8; 20] {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..20].copy_from_slice(&Sha1::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha1::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha1::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters and `?` any one character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Parse `host` or `[host]:port`
+fn parse_host_name(text: &str) -> Option<HostName> {
+    if let Some(rest) = text.strip_prefix('[') {
+        let (name, port) = rest.split_once("]:")?;
+        return Some(HostName { name: name.to_owned(), port: port.parse().ok()? });
+    }
+    Some(HostName { name: text.to_owned(), port: DEFAULT_PORT })
+}
+
+fn parse_pattern(text: &str) -> Option<HostPattern> {
+    use base64::Engine;
+    if let Some(hashed) = text.strip_prefix("|1|") {
+        let (salt, hash) = hashed.split_once('|')?;
+        let engine = base64::engine::general_purpose::STANDARD;
+        return Some(HostPattern::Hashed { salt: engine.decode(salt).ok()?, hash: engine.decode(hash).ok()?.try_into().ok()? });
+    }
+    let (negated, text) = text.strip_prefix('!').map_or((false, text), |rest| (true, rest));
+    Some(HostPattern::Name { pattern: parse_host_name(text)?, negated })
+}
+
+/// Parse a `known_hosts` file, including hashed host names and `@cert-authority`/`@revoked` markers.
+/// # Errors
+/// On lines without a key type and base64 key, or with an unknown marker or malformed host pattern
+pub fn parse(data: &str) -> Result<Vec<Entry>, SshScanErr> {
+    let mut res = Vec::new();
+    for (line_no, line) in data.lines().enumerate() {
+        let err = |what: &str| SshScanErr::InvalidArgument(format!("known_hosts line {}: {what}", line_no + 1));
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_ascii_whitespace().peekable();
+        let marker = match fields.peek() {
+            Some(&"@cert-authority") => Some(Marker::CertAuthority),
+            Some(&"@revoked") => Some(Marker::Revoked),
+            Some(m) if m.starts_with('@') => return Err(err(&format!("unknown marker {m}"))),
+            _ => None,
+        };
+        if marker.is_some() {
+            fields.next();
+        }
+        let (Some(hosts), Some(key_type), Some(blob)) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(err("expected hosts, key type and key"));
+        };
+        let key = HostKey::from_blob(key_type, blob).ok_or_else(|| err("invalid base64 key"))?;
+        let patterns = hosts.split(',').map(|p| parse_pattern(p).ok_or_else(|| err(&format!("invalid host pattern {p}")))).collect::<Result<_, _>>()?;
+        res.push(Entry { marker, patterns, key });
+    }
+    Ok(res)
+}
+
+/// Host names that can be scanned: plain, non-negated names of host key entries.
+/// Hashed names and wildcard patterns cannot be turned back into targets.
+#[must_use]
+pub fn targets(entries: &[Entry]) -> Vec<HostName> {
+    let mut res: Vec<HostName> = entries
+        .iter()
+        .filter(|e| e.marker.is_none())
+        .flat_map(|e| &e.patterns)
+        .filter_map(|p| match p {
+            HostPattern::Name { pattern, negated: false } if !pattern.name.contains(['*', '?']) => Some(pattern.clone()),
+            _ => None,
+        })
+        .collect();
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// Check every open host:port against `known_hosts`: keys that differ from the expected ones and
+/// keys marked `@revoked`. Hosts are matched by address and by the names nmap reported for them.
+#[must_use]
+pub fn verify(entries: &[Entry], hosts: &[Host]) -> Vec<Finding> {
+    let mut res = Vec::new();
+    for host in hosts {
+        let names: Vec<&str> = std::iter::once(host.addr.as_str()).chain(host.hostnames.iter().map(String::as_str)).collect();
+        for hp in host.port_states.iter().filter(|hp| hp.state) {
+            let applicable: Vec<&Entry> = entries.iter().filter(|e| e.matches(&names, hp.portid)).collect();
+            let expected: Vec<HostKey> = applicable.iter().filter(|e| e.marker.is_none()).map(|e| e.key.clone()).collect();
+            res.extend(baseline::host_key_changes(&host.addr, hp.portid, &expected, &hp.host_keys));
+            for revoked in applicable.iter().filter(|e| e.marker == Some(Marker::Revoked)) {
+                if let Some(key) = hp.host_keys.iter().find(|k| k.fingerprint == revoked.key.fingerprint) {
+                    res.push(Finding {
+                        addr: host.addr.clone(),
+                        portid: hp.portid,
+                        kind: FindingKind::RevokedHostKey,
+                        severity: FindingKind::RevokedHostKey.default_severity(),
+                        subject: key.key_type.clone(),
+                        message: format!("{} host key {} is marked @revoked", key.key_type, key.fingerprint),
+                    });
+                }
+            }
+        }
+    }
+    res
+}
+
+/// A fresh salt for hashed host names. std's `RandomState` is randomly seeded per process,
+/// which is enough to keep salts unique without pulling in a random number generator.
+fn random_salt() -> [u8; 20] {
+    use std::hash::{BuildHasher, Hasher};
+    let state = std::collections::hash_map::RandomState::new();
+    let mut salt = [0u8; 20];
+    for (i, chunk) in salt.chunks_mut(8).enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_usize(i);
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes()[..chunk.len()]);
+    }
+    salt
+}
+
+/// Write the host keys of every open host:port as a `known_hosts` file, listing the address and
+/// host names of each host. With `hashed` every name gets its own line with a hashed `|1|` name,
+/// like `ssh-keygen -H`. Keys nmap reported without the public key blob are skipped.
+#[must_use]
+pub fn generate(hosts: &[Host], hashed: bool) -> String {
+    use base64::Engine;
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut out = String::new();
+    for host in hosts {
+        for hp in host.port_states.iter().filter(|hp| hp.state) {
+            let names: Vec<String> = std::iter::once(&host.addr)
+                .chain(&host.hostnames)
+                .map(|name| HostName { name: name.clone(), port: hp.portid }.to_known_hosts())
+                .collect();
+            for key in &hp.host_keys {
+                let Some(blob) = &key.key else { continue };
+                if hashed {
+                    for name in &names {
+                        let salt = random_salt();
+                        let hash = hmac_sha1(&salt, name.as_bytes());
+                        let _ = writeln!(out, "|1|{}|{} {} {blob}", engine.encode(salt), engine.encode(hash), key.key_type);
+                    }
+                } else {
+                    let _ = writeln!(out, "{} {} {blob}", names.join(","), key.key_type);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    const KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+
+    /// `ssh-keygen -H` output for `bastion.example.com` and `[bastion.example.com]:2222`
+    const HASHED: &str = "\
+|1|gGp9eeF53PC6KmQrc9gtiDtyUpE=|2Fja9GbQe1tgYYgZxpd/5cNxp7g= ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl
+|1|eO+5AkhOiZU3lFO+ZXdk2D7npgk=|WOxGuBA3rLVpjRC+/vqCe/dLGTU= ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl
+";
+
+    fn host_with_key(addr: &str, portid: u16, key: &str) -> Host {
+        let mut port = fixtures::port(portid, fixtures::algos(["curve25519-sha256", "ssh-ed25519", "aes128-ctr", "hmac-sha2-256", "none"]));
+        port.host_keys.push(HostKey::from_blob("ssh-ed25519", key).unwrap());
+        fixtures::host(addr, vec![port])
+    }
+
+    #[test]
+    fn matches_ssh_keygen_hashed_names() {
+        let entries = parse(HASHED).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].matches(&["bastion.example.com"], 22));
+        assert!(!entries[0].matches(&["bastion.example.com"], 2222));
+        assert!(!entries[0].matches(&["other.example.com"], 22));
+        assert!(entries[1].matches(&["10.0.0.1", "bastion.example.com"], 2222));
+        assert!(!entries[1].matches(&["bastion.example.com"], 22));
+    }
+
+    #[test]
+    fn matches_globs_and_negations() {
+        let entries = parse(&format!("*.example.com,!secret.example.com,10.0.0.? ssh-ed25519 {KEY}\n")).unwrap();
+        let entry = &entries[0];
+        assert!(entry.matches(&["Bastion.Example.com"], 22));
+        assert!(entry.matches(&["10.0.0.7"], 22));
+        assert!(!entry.matches(&["10.0.0.17"], 22));
+        assert!(!entry.matches(&["secret.example.com"], 22));
+        // The negation wins even when another name of the host matches
+        assert!(!entry.matches(&["10.0.0.7", "secret.example.com"], 22));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b", "axxbc"));
+        assert_eq!(targets(entries.as_slice()), []);
+    }
+
+    #[test]
+    fn computes_hmac_sha1() {
+        // RFC 2202 test case 2
+        let mac = hmac_sha1(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(mac.iter().map(|b| format!("{b:02x}")).collect::<String>(), "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+    }
+
+    #[test]
+    fn reports_changed_and_revoked_keys() {
+        let other = "AAAAC3NzaC1lZDI1NTE5AAAAIHb1Zmqv6H8d1pqCjGyQbX3j6aEWtpYx2ZrTJcXv0s0U";
+        let entries = parse(&format!("10.0.0.1 ssh-ed25519 {other}\n@revoked * ssh-ed25519 {KEY}\n")).unwrap();
+        assert_eq!(entries[1].marker, Some(Marker::Revoked));
+        let findings = verify(&entries, &[host_with_key("10.0.0.1", 22, KEY)]);
+        let kinds: Vec<FindingKind> = findings.iter().map(|f| f.kind).collect();
+        assert_eq!(kinds, [FindingKind::HostKeyChanged, FindingKind::RevokedHostKey]);
+        assert!(verify(&entries, &[host_with_key("10.0.0.1", 22, other)]).is_empty());
+    }
+
+    #[test]
+    fn generated_lines_parse_back() {
+        let mut host = host_with_key("10.0.0.1", 2222, KEY);
+        host.hostnames.push("bastion.example.com".to_owned());
+        let plain = generate(std::slice::from_ref(&host), false);
+        assert_eq!(plain, format!("[10.0.0.1]:2222,[bastion.example.com]:2222 ssh-ed25519 {KEY}\n"));
+        let hashed = generate(&[host], true);
+        assert!(hashed.lines().all(|line| line.starts_with("|1|")), "{hashed}");
+        let entries = parse(&hashed).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].matches(&["10.0.0.1"], 2222));
+        assert!(entries[1].matches(&["bastion.example.com"], 2222));
+        assert!(!entries[1].matches(&["bastion.example.com"], 22));
+        assert_eq!(entries[0].key, HostKey::from_blob("ssh-ed25519", KEY).unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let err = parse(&format!("# comment\n@trusted host ssh-ed25519 {KEY}\n")).unwrap_err();
+        assert!(err.to_string().contains("line 2: unknown marker @trusted"), "{err}");
+        assert!(parse(&format!("|1|c2FsdA==| ssh-ed25519 {KEY}\n")).is_err());
+        assert!(parse(&format!("|1|not base64|aGFzaA== ssh-ed25519 {KEY}\n")).is_err());
+        assert!(parse(&format!("[host]2222 ssh-ed25519 {KEY}\n")).is_err());
+        assert!(parse("host ssh-ed25519\n").is_err());
+        assert!(parse("host ssh-ed25519 !!!\n").is_err());
+    }
+}
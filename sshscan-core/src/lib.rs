@@ -1,6 +1,24 @@
 pub mod xml;
 pub mod agg_data;
 pub mod html;
+pub mod strength;
+pub mod negotiate;
+mod chart;
+pub mod findings;
+pub mod template;
+pub mod markdown;
+pub mod text;
+pub mod sarif;
+pub mod junit;
+pub mod metrics;
+pub mod json;
+pub mod trend;
+pub mod baseline;
+pub mod known_hosts;
+pub mod sshfp;
+pub mod targets;
+#[cfg(feature = "sqlite")]
+pub mod history;
 
 use std::num::ParseIntError;
 
@@ -9,9 +27,76 @@ pub enum SshScanErr {
     XMLInvalid,
     ParseIntError(ParseIntError),
     XMLParseFailure(xmltree::ParseError),
+    JSONInvalid,
+    JSONParseFailure(serde_json::Error),
+    InvalidProfile(String),
+    Template(String),
+    InvalidArgument(String),
+    Storage(String),
+    Nmap(NmapError),
     Other(String),
 }
 
+/// Ways running nmap can fail. Variants with a `String` carry what nmap wrote to stderr.
+#[derive(Debug)]
+pub enum NmapError {
+    NotFound,
+    /// The nmap found in `$PATH` cannot be run, e.g. missing execute permission
+    NotExecutable(String),
+    Spawn(std::io::Error),
+    /// The scan needs privileges nmap was not run with
+    PrivilegesRequired(String),
+    /// nmap could not parse or resolve the targets
+    InvalidTarget(String),
+    /// Non-zero exit status, `None` when nmap was killed by a signal
+    Failed { status: Option<i32>, stderr: String },
+    InvalidOutput,
+}
+
+impl NmapError {
+    /// Classify a failed nmap run by its stderr
+    #[must_use]
+    pub fn from_failure(status: Option<i32>, stderr: &str) -> Self {
+        let stderr = stderr.trim().to_owned();
+        let lower = stderr.to_ascii_lowercase();
+        if ["root privileges", "operation not permitted", "requires root", "failed to open device"].iter().any(|m| lower.contains(m)) {
+            Self::PrivilegesRequired(stderr)
+        } else if ["failed to resolve", "illegal", "invalid target", "no targets were specified"].iter().any(|m| lower.contains(m)) {
+            Self::InvalidTarget(stderr)
+        } else {
+            Self::Failed { status, stderr }
+        }
+    }
+}
+
+impl core::fmt::Display for NmapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stderr = match self {
+            Self::NotFound => return f.write_str("nmap not found in $PATH"),
+            Self::NotExecutable(path) => return write!(f, "{path} is not executable"),
+            Self::Spawn(e) => return write!(f, "failed to run nmap: {e}"),
+            Self::InvalidOutput => return f.write_str("nmap output is not valid UTF-8"),
+            Self::PrivilegesRequired(stderr) => {
+                f.write_str("nmap needs more privileges for this scan, run it as root")?;
+                stderr
+            }
+            Self::InvalidTarget(stderr) => {
+                f.write_str("nmap rejected the targets")?;
+                stderr
+            }
+            Self::Failed { status: Some(status), stderr } => {
+                write!(f, "nmap exited with status {status}")?;
+                stderr
+            }
+            Self::Failed { status: None, stderr } => {
+                f.write_str("nmap was terminated by a signal")?;
+                stderr
+            }
+        };
+        if stderr.is_empty() { Ok(()) } else { write!(f, ":\n{stderr}") }
+    }
+}
+
 impl From<ParseIntError> for SshScanErr {
     fn from(value: ParseIntError) -> Self {
         Self::ParseIntError(value)
@@ -22,6 +107,22 @@ impl From<xmltree::ParseError> for SshScanErr {
         Self::XMLParseFailure(value)
     }
 }
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for SshScanErr {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+impl From<NmapError> for SshScanErr {
+    fn from(value: NmapError) -> Self {
+        Self::Nmap(value)
+    }
+}
+impl From<serde_json::Error> for SshScanErr {
+    fn from(value: serde_json::Error) -> Self {
+        Self::JSONParseFailure(value)
+    }
+}
 impl core::fmt::Display for SshScanErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -36,6 +137,30 @@ impl core::fmt::Display for SshScanErr {
                 f.write_str("XML Parsing Failure\n - Inner:")?;
                 parse_error.fmt(f)
             },
+            Self::JSONInvalid => {
+                f.write_str("JSON Data Invalid")
+            },
+            Self::JSONParseFailure(parse_error) => {
+                f.write_str("JSON Parsing Failure\n - Inner:")?;
+                parse_error.fmt(f)
+            },
+            Self::InvalidProfile(xplain) => {
+                f.write_str("Invalid client profile: ")?;
+                xplain.fmt(f)
+            },
+            Self::Template(xplain) => {
+                f.write_str("Template error: ")?;
+                xplain.fmt(f)
+            },
+            Self::InvalidArgument(xplain) => {
+                f.write_str("Invalid argument: ")?;
+                xplain.fmt(f)
+            },
+            Self::Storage(xplain) => {
+                f.write_str("Storage error: ")?;
+                xplain.fmt(f)
+            },
+            Self::Nmap(nmap_error) => nmap_error.fmt(f),
             Self::Other(xplain) => {
                 f.write_str("Unspecified error: ")?;
                 xplain.fmt(f)
@@ -45,14 +170,46 @@ impl core::fmt::Display for SshScanErr {
 }
 impl core::error::Error for SshScanErr {}
 
+/// Metadata about the nmap run, taken from the `nmaprun` and `runstats` elements
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Default, Clone)]
+pub struct ScanInfo {
+    pub args: Option<String>,
+    pub nmap_version: Option<String>,
+    /// Unix timestamp of the scan start
+    pub start: Option<i64>,
+    /// Unix timestamp of the scan end
+    pub end: Option<i64>,
+}
+
 #[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 #[derive(Debug, Default, Clone)]
 pub struct Host {
     pub status: HostStatus,
     pub addr: String,
+    /// Names the host was given as or resolved to by nmap
+    pub hostnames: Vec<String>,
     pub port_states: Vec<Description>,
 }
 
+/// `addr:port` for display, with IPv6 addresses in brackets: `[::1]:22`
+#[must_use]
+pub fn host_port(addr: &str, port: u16) -> String {
+    if addr.contains(':') {
+        format!("[{addr}]:{port}")
+    } else {
+        format!("{addr}:{port}")
+    }
+}
+
+/// HTML id of a host:port, made of letters, digits, `.` and `-` only so IPv6 addresses
+/// stay unambiguous and usable as URL fragments: `host-10.0.0.1-22`, `host---1-22` for `::1`
+#[must_use]
+pub fn anchor_id(addr: &str, port: u16) -> String {
+    let sanitized: String = addr.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' }).collect();
+    format!("host-{sanitized}-{port}")
+}
+
 #[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 #[derive(Debug, Clone, Default)]
 pub enum HostStatus {
@@ -62,6 +219,17 @@ pub enum HostStatus {
     Unknown,
 }
 
+impl HostStatus {
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
 impl From<&str> for HostStatus {
     fn from(value: &str) -> Self {
         match (value.eq_ignore_ascii_case("up"), value.eq_ignore_ascii_case("down")) {
@@ -79,6 +247,42 @@ pub struct Description {
     pub state: bool,
     pub algos: Algos,
     pub product: Option<String>,
+    /// HASSHServer fingerprint, present when algorithms were enumerated
+    pub hassh: Option<String>,
+    /// Server accepts SSH protocol version 1
+    pub sshv1: bool,
+    /// Host keys reported by the ssh-hostkey script
+    pub host_keys: Vec<HostKey>,
+}
+
+/// A server host key as reported by nmap's ssh-hostkey script
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostKey {
+    /// Key type, e.g. `ssh-ed25519`
+    pub key_type: String,
+    pub bits: Option<u32>,
+    /// MD5 fingerprint as hex without separators, the format nmap reports
+    pub fingerprint: String,
+    /// Base64 public key blob, when nmap included it
+    pub key: Option<String>,
+}
+
+impl HostKey {
+    /// Build from a key type and base64 public key blob as found in `known_hosts`,
+    /// `None` when the blob is not valid base64
+    #[must_use]
+    pub fn from_blob(key_type: &str, blob: &str) -> Option<Self> {
+        use base64::Engine;
+        use md5::{Digest, Md5};
+        let data = base64::engine::general_purpose::STANDARD.decode(blob).ok()?;
+        let fingerprint = Md5::digest(&data).iter().fold(String::with_capacity(32), |mut acc, b| {
+            use std::fmt::Write;
+            let _ = write!(acc, "{b:02x}");
+            acc
+        });
+        Some(Self { key_type: key_type.to_owned(), bits: None, fingerprint, key: Some(blob.to_owned()) })
+    }
 }
 
 #[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
@@ -92,6 +296,15 @@ pub struct Algos {
 }
 
 impl Algos {
+    /// Category keys accepted by the `Index` impl, in table column order
+    pub const KEYS: [&'static str; 5] = [
+        "kex_algorithms",
+        "server_host_key_algorithms",
+        "encryption_algorithms",
+        "mac_algorithms",
+        "compression_algorithms",
+    ];
+
     const fn longest(&self) -> usize {
         let mut len = 0usize;
         if len < self.kex.len() { len = self.kex.len(); }
@@ -101,6 +314,27 @@ impl Algos {
         if len < self.compression.len() { len = self.compression.len(); }
         len
     }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.kex.is_empty() && self.encryption.is_empty() && self.mac.is_empty() && self.compression.is_empty()
+    }
+
+    /// Compute the HASSHServer fingerprint: MD5 of `kex;ciphers;macs;compression`
+    /// with each list comma separated in server preference order.
+    #[must_use]
+    pub fn hassh_server(&self) -> String {
+        use md5::{Digest, Md5};
+        let hassh_str = [&self.kex, &self.encryption, &self.mac, &self.compression]
+            .map(|list| list.join(","))
+            .join(";");
+        let digest = Md5::digest(hassh_str.as_bytes());
+        digest.iter().fold(String::with_capacity(32), |mut acc, b| {
+            use std::fmt::Write;
+            let _ = write!(acc, "{b:02x}");
+            acc
+        })
+    }
 }
 
 impl std::ops::Index<&str> for Algos {
@@ -129,4 +363,61 @@ impl std::ops::IndexMut<&str> for Algos {
             _ => panic!("Invalid index"),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_port_brackets_ipv6() {
+        assert_eq!(host_port("::1", 22), "[::1]:22");
+        assert_eq!(host_port("10.0.0.1", 22), "10.0.0.1:22");
+    }
+
+    #[test]
+    fn hassh_server_matches_reference() {
+        // OpenSSH 7.x server example from the HASSH project README
+        let algos = fixtures::algos([
+            "curve25519-sha256@libssh.org,ecdh-sha2-nistp256,ecdh-sha2-nistp384,ecdh-sha2-nistp521,diffie-hellman-group-exchange-sha256,diffie-hellman-group14-sha1",
+            "ssh-rsa,rsa-sha2-512,rsa-sha2-256,ecdsa-sha2-nistp256,ssh-ed25519",
+            "chacha20-poly1305@openssh.com,aes128-ctr,aes192-ctr,aes256-ctr,aes128-gcm@openssh.com,aes256-gcm@openssh.com",
+            "umac-64-etm@openssh.com,umac-128-etm@openssh.com,hmac-sha2-256-etm@openssh.com,hmac-sha2-512-etm@openssh.com,hmac-sha1-etm@openssh.com,umac-64@openssh.com,umac-128@openssh.com,hmac-sha2-256,hmac-sha2-512,hmac-sha1",
+            "none,zlib@openssh.com",
+        ]);
+        // Host key algorithms are not part of HASSHServer
+        assert_eq!(algos.hassh_server(), "d43d91bc39d5aaed819ad9f6b57b7348");
+        // Empty lists still keep their separators: MD5 of ";;;"
+        assert_eq!(Algos::default().hassh_server(), "95420f9d932ddd22833f17b96a80bedb");
+    }
+
+    #[test]
+    fn anchor_id_has_no_colons() {
+        assert_eq!(anchor_id("::1", 22), "host---1-22");
+        assert!(!anchor_id("2001:db8::1", 2222).contains(':'));
+        assert_eq!(anchor_id("10.0.0.1", 22), "host-10.0.0.1-22");
+    }
+}
+
+/// Builders for hosts used by the unit tests of several modules
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    /// Algorithms from comma separated lists, in [`Algos::KEYS`] order
+    pub fn algos(lists: [&str; 5]) -> Algos {
+        let mut algos = Algos::default();
+        for (category, list) in Algos::KEYS.into_iter().zip(lists) {
+            algos[category] = list.split(',').filter(|a| !a.is_empty()).map(str::to_owned).collect();
+        }
+        algos
+    }
+
+    /// An open port offering `algos`
+    pub fn port(portid: u16, algos: Algos) -> Description {
+        Description { portid, state: true, hassh: Some(algos.hassh_server()), algos, ..Description::default() }
+    }
+
+    pub fn host(addr: &str, port_states: Vec<Description>) -> Host {
+        Host { status: HostStatus::Up, addr: addr.to_owned(), hostnames: Vec::new(), port_states }
+    }
+}
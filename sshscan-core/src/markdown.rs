@@ -0,0 +1,227 @@
+use std::fmt::Write;
+use crate::{
+    agg_data::{self, AggregatedData, Summary},
+    findings::Finding,
+    html::{HOST_HEADERS, ReportOptions},
+    strength::Grade,
+    trend::{self, Trend},
+    host_port, Host,
+};
+
+/// Escape text for use in GitHub-flavored Markdown, including inside table cells
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#') {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Generate a Markdown report with a summary, per-host algorithm tables and per-algorithm host lists.
+#[must_use]
+pub fn generate(hosts: &[Host], agg_data: &AggregatedData, key_changes: &[Finding], options: &ReportOptions) -> String {
+    let mut out = String::new();
+    let title = options.title.as_deref().map_or_else(|| "sshscan Report".to_owned(), escape);
+    let _ = writeln!(out, "# {title} - Generated: {}\n", options.now());
+    if let Some(subtitle) = &options.subtitle {
+        let _ = writeln!(out, "{}\n", escape(subtitle));
+    }
+    if !key_changes.is_empty() {
+        let _ = writeln!(out, "## :warning: Host key changes\n");
+        for change in key_changes {
+            let _ = writeln!(out, "- **{}**: {}", escape(&host_port(&change.addr, change.portid)), escape(&change.message));
+        }
+        out.push('\n');
+    }
+    if !hosts.is_empty() {
+        write_summary(&mut out, &Summary::build_from_hosts(hosts));
+        write_hosts(&mut out, hosts);
+        write_algo_lists(&mut out, agg_data);
+    }
+    if let Some(footer) = &options.footer {
+        let _ = writeln!(out, "---\n\n{}", escape(footer));
+    }
+    out
+}
+
+fn write_summary(out: &mut String, summary: &Summary) {
+    let _ = writeln!(out, "## Summary\n");
+    let _ = writeln!(out, "| Hosts up | Hosts down | Open ports | Closed / filtered ports |");
+    let _ = writeln!(out, "|---:|---:|---:|---:|");
+    let _ = writeln!(out, "| {} | {} | {} | {} |\n", summary.hosts_up, summary.hosts_down, summary.ports_open, summary.ports_closed);
+    let _ = writeln!(out, "| Grade | Count |\n|---|---:|");
+    for (grade, count) in Grade::ALL.iter().zip(summary.grades) {
+        let _ = writeln!(out, "| {grade} | {count} |");
+    }
+    let _ = writeln!(out, "\n| Software | Count |\n|---|---:|");
+    for (product, count) in summary.products_by_count() {
+        let _ = writeln!(out, "| {} | {count} |", escape(product));
+    }
+    out.push('\n');
+}
+
+fn write_hosts(out: &mut String, hosts: &[Host]) {
+    let _ = writeln!(out, "## Hosts\n");
+    for host in hosts {
+        for t in agg_data::wrangle_host_to_table(host, &[]) {
+            let _ = write!(out, "### {}", host_port(&host.addr, t.port));
+            if let Some(product) = &t.product {
+                let _ = write!(out, " {}", escape(product));
+            }
+            if let Some(grade) = t.grade {
+                let _ = write!(out, " (Grade {grade})");
+            }
+            out.push_str("\n\n");
+            if let Some(hassh) = &t.hassh {
+                let _ = writeln!(out, "HASSH: `{hassh}`\n");
+            }
+            for warning in &t.warnings {
+                let _ = writeln!(out, "- **Weak preference:** {}", escape(&warning.to_string()));
+            }
+            if !t.warnings.is_empty() {
+                out.push('\n');
+            }
+            if t.algos.is_empty() {
+                continue;
+            }
+            out.push_str("| # |");
+            for header in HOST_HEADERS {
+                let _ = write!(out, " {} |", header.title);
+            }
+            out.push_str("\n|---:|");
+            for _ in HOST_HEADERS {
+                out.push_str("---|");
+            }
+            out.push('\n');
+            for (rank, row) in t.algos.iter().enumerate() {
+                let _ = write!(out, "| {} |", rank + 1);
+                for entry in row {
+                    if entry.is_empty() {
+                        out.push_str("  |");
+                    } else {
+                        let _ = write!(out, " `{entry}` |");
+                    }
+                }
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+    }
+}
+
+fn write_algo_lists(out: &mut String, agg_data: &AggregatedData) {
+    for header in HOST_HEADERS {
+        let _ = writeln!(out, "## {}\n", header.title);
+        for (algo, offers) in agg_data::by_count(&agg_data[header.data_key]) {
+            let host_ports: Vec<String> = offers
+                .iter()
+                .map(|o| format!("{} (#{})", host_port(&o.host.addr, o.portid), o.rank + 1))
+                .collect();
+            let _ = writeln!(
+                out,
+                "- `{algo}` (Count: {}, First choice: {}): {}",
+                offers.len(),
+                agg_data::first_choice_count(offers),
+                host_ports.join(", "),
+            );
+        }
+        out.push('\n');
+    }
+}
+
+/// Generate a Markdown trend report, see [`crate::html::generate_trend`]
+#[must_use]
+pub fn generate_trend(trend: &Trend, options: &ReportOptions) -> String {
+    let mut out = String::new();
+    let title = options.title.as_deref().map_or_else(|| "sshscan Trend Report".to_owned(), escape);
+    let _ = writeln!(out, "# {title} - Generated: {}\n", options.now());
+    if let Some(subtitle) = &options.subtitle {
+        let _ = writeln!(out, "{}\n", escape(subtitle));
+    }
+    let labels: Vec<String> = (0..trend.scans.len()).map(|idx| escape(&trend.scan_label(idx, options))).collect();
+
+    let _ = writeln!(out, "## Grades over time\n");
+    out.push_str("| Scan | Open ports |");
+    for grade in Grade::ALL {
+        let _ = write!(out, " {grade} |");
+    }
+    out.push_str("\n|---|---:|");
+    for _ in Grade::ALL {
+        out.push_str("---:|");
+    }
+    out.push('\n');
+    for (label, scan) in labels.iter().zip(&trend.scans) {
+        let _ = write!(out, "| {label} | {} |", scan.ports_open);
+        for count in scan.grades {
+            let _ = write!(out, " {count} |");
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+
+    let _ = writeln!(out, "## Algorithm prevalence over time\n");
+    for header in HOST_HEADERS {
+        let _ = write!(out, "### {}\n\n| Algorithm | Rating |", header.title);
+        for label in &labels {
+            let _ = write!(out, " {label} |");
+        }
+        out.push_str("\n|---|---|");
+        for _ in &labels {
+            out.push_str("---:|");
+        }
+        out.push('\n');
+        for p in trend.prevalence.iter().filter(|p| p.category == header.title) {
+            let _ = write!(out, "| `{}` | {} |", p.algorithm, p.strength);
+            for count in &p.counts {
+                let _ = write!(out, " {count} |");
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "## Changes between scans\n");
+    for transition in &trend.transitions {
+        let _ = writeln!(out, "### {} to {}\n", labels[transition.scan - 1], labels[transition.scan]);
+        if transition.is_empty() {
+            out.push_str("No grade changes\n");
+        }
+        for (what, list) in [("Fixed", &transition.fixed), ("Regressed", &transition.regressed)] {
+            for change in list {
+                let _ = writeln!(out, "- **{what}:** {} ({} to {})", escape(&change.host_port), change.before, change.after);
+            }
+        }
+        for (what, list) in [("Appeared", &transition.appeared), ("Disappeared", &transition.disappeared)] {
+            for host_port in list {
+                let _ = writeln!(out, "- **{what}:** {}", escape(host_port));
+            }
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "## Time to remediate\n");
+    let _ = writeln!(out, "| Finding | Fixed | Mean time to remediate |\n|---|---:|---:|");
+    for (kind, seconds, count) in trend.mean_time_to_remediate() {
+        let _ = writeln!(out, "| {} | {count} | {} |", kind.description(), trend::format_duration(seconds));
+    }
+    let _ = writeln!(out, "\n| Host:port | Finding | Subject | First seen | Fixed | Time to remediate |\n|---|---|---|---|---|---:|");
+    for r in &trend.remediations {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            escape(&r.host_port),
+            r.kind,
+            escape(&r.subject),
+            labels[r.first_seen],
+            r.fixed.map_or("Open", |idx| labels[idx].as_str()),
+            r.duration(&trend.scans).map(trend::format_duration).unwrap_or_default(),
+        );
+    }
+    if let Some(footer) = &options.footer {
+        let _ = writeln!(out, "\n---\n\n{}", escape(footer));
+    }
+    out
+}
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+use crate::{
+    agg_data::{self, AggregatedData, Summary},
+    findings::{Finding, FindingKind, Severity},
+    html::HOST_HEADERS,
+    strength::{self, Grade},
+    Host, ScanInfo,
+};
+
+/// Escape a label value for the Prometheus text exposition format
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge");
+}
+
+/// Generate metrics in the Prometheus text exposition format, suitable for node_exporter's
+/// textfile collector. `timestamp` is used when the scan data carries no time of its own.
+#[must_use]
+pub fn generate(hosts: &[Host], agg_data: &AggregatedData, info: &ScanInfo, findings: &[Finding], timestamp: i64) -> String {
+    let mut out = String::new();
+    let summary = Summary::build_from_hosts(hosts);
+
+    header(&mut out, "sshscan_scan_timestamp_seconds", "Unix time the scan finished.");
+    let _ = writeln!(out, "sshscan_scan_timestamp_seconds {}", info.end.or(info.start).unwrap_or(timestamp));
+
+    header(&mut out, "sshscan_hosts", "Scanned hosts by status.");
+    for (status, count) in [("up", summary.hosts_up), ("down", summary.hosts_down), ("unknown", summary.hosts_unknown)] {
+        let _ = writeln!(out, "sshscan_hosts{{status=\"{status}\"}} {count}");
+    }
+
+    header(&mut out, "sshscan_host_ports", "Scanned host:ports by state.");
+    let _ = writeln!(out, "sshscan_host_ports{{state=\"open\"}} {}", summary.ports_open);
+    let _ = writeln!(out, "sshscan_host_ports{{state=\"closed\"}} {}", summary.ports_closed);
+
+    header(&mut out, "sshscan_host_grade", "Grade of each host:port, 0 for A through 4 for F.");
+    for host in hosts {
+        for host_port in &host.port_states {
+            if let Some(grade) = strength::grade(&host_port.algos) {
+                let _ = writeln!(
+                    out,
+                    "sshscan_host_grade{{host=\"{}\",port=\"{}\"}} {}",
+                    escape(&host.addr),
+                    host_port.portid,
+                    grade as usize,
+                );
+            }
+        }
+    }
+
+    header(&mut out, "sshscan_grade_host_ports", "Host:ports per grade.");
+    for (grade, count) in Grade::ALL.iter().zip(summary.grades) {
+        let _ = writeln!(out, "sshscan_grade_host_ports{{grade=\"{grade}\"}} {count}");
+    }
+
+    header(&mut out, "sshscan_algorithm_offered", "Host:ports offering each algorithm.");
+    for header in HOST_HEADERS {
+        let category = header.data_key.trim_end_matches("_algos");
+        for (algo, offers) in agg_data::by_count(&agg_data[header.data_key]) {
+            let _ = writeln!(
+                out,
+                "sshscan_algorithm_offered{{category=\"{category}\",algorithm=\"{}\",strength=\"{}\"}} {}",
+                escape(algo),
+                strength::classify(header.algo_key, algo),
+                offers.len(),
+            );
+        }
+    }
+
+    header(&mut out, "sshscan_algorithm_first_choice", "Host:ports preferring each algorithm.");
+    for header in HOST_HEADERS {
+        let category = header.data_key.trim_end_matches("_algos");
+        for (algo, offers) in agg_data::by_count(&agg_data[header.data_key]) {
+            let _ = writeln!(
+                out,
+                "sshscan_algorithm_first_choice{{category=\"{category}\",algorithm=\"{}\"}} {}",
+                escape(algo),
+                agg_data::first_choice_count(offers),
+            );
+        }
+    }
+
+    // Every series is written, also when 0, so alerts see findings disappear instead of going stale
+    header(&mut out, "sshscan_findings", "Findings by kind and severity.");
+    for kind in FindingKind::ALL {
+        for severity in [Severity::Note, Severity::Warning, Severity::Error] {
+            let count = findings.iter().filter(|f| f.kind == kind && f.severity == severity).count();
+            let _ = writeln!(out, "sshscan_findings{{kind=\"{kind}\",severity=\"{severity}\"}} {count}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn render(findings: &[Finding]) -> String {
+        let algos = fixtures::algos(["curve25519-sha256", "ssh-ed25519", "aes128-ctr,we\"ird\\cipher", "hmac-sha2-256", "none"]);
+        let hosts = vec![fixtures::host("10.0.0.1", vec![fixtures::port(22, algos)])];
+        let agg_data = AggregatedData::build_from_hosts(&hosts);
+        let info = ScanInfo { end: Some(1_700_000_000), ..ScanInfo::default() };
+        generate(&hosts, &agg_data, &info, findings, 0)
+    }
+
+    #[test]
+    fn declares_every_family_before_its_samples() {
+        let out = render(&[]);
+        let mut declared: Vec<&str> = Vec::new();
+        let mut lines = out.lines().peekable();
+        while let Some(line) = lines.next() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let name = rest.split(' ').next().unwrap();
+                assert_eq!(lines.next(), Some(format!("# TYPE {name} gauge").as_str()));
+                declared.push(name);
+            } else {
+                let name = line.split(['{', ' ']).next().unwrap();
+                assert_eq!(declared.last(), Some(&name), "{line}");
+            }
+        }
+        assert_eq!(
+            declared,
+            [
+                "sshscan_scan_timestamp_seconds",
+                "sshscan_hosts",
+                "sshscan_host_ports",
+                "sshscan_host_grade",
+                "sshscan_grade_host_ports",
+                "sshscan_algorithm_offered",
+                "sshscan_algorithm_first_choice",
+                "sshscan_findings",
+            ],
+        );
+        assert!(out.contains("sshscan_scan_timestamp_seconds 1700000000\n"), "{out}");
+        assert!(out.contains("sshscan_host_grade{host=\"10.0.0.1\",port=\"22\"} "), "{out}");
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+        let out = render(&[]);
+        assert!(out.contains("algorithm=\"we\\\"ird\\\\cipher\""), "{out}");
+    }
+
+    #[test]
+    fn writes_zero_series() {
+        let finding = Finding {
+            addr: "10.0.0.1".to_owned(),
+            portid: 22,
+            kind: FindingKind::WeakMac,
+            severity: Severity::Warning,
+            subject: "hmac-md5".to_owned(),
+            message: String::new(),
+        };
+        let out = render(&[finding]);
+        assert!(out.contains("sshscan_hosts{status=\"down\"} 0\n"), "{out}");
+        assert!(out.contains("sshscan_host_ports{state=\"closed\"} 0\n"), "{out}");
+        assert!(out.contains("sshscan_grade_host_ports{grade=\"F\"} 0\n"), "{out}");
+        let findings: Vec<&str> = out.lines().filter(|line| line.starts_with("sshscan_findings{")).collect();
+        assert_eq!(findings.len(), FindingKind::ALL.len() * 3);
+        assert!(findings.contains(&"sshscan_findings{kind=\"weak-mac\",severity=\"warning\"} 1"), "{out}");
+        assert_eq!(findings.iter().filter(|line| line.ends_with(" 0")).count(), findings.len() - 1);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{Algos, SshScanErr};
+
+/// The algorithm lists a client sends in its KEXINIT, in client preference order
+#[derive(Debug, Clone)]
+pub struct ClientProfile {
+    pub name: String,
+    pub algos: Algos,
+}
+
+/// What negotiation selects for one algorithm category
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selected {
+    Algorithm(String),
+    /// No MAC is negotiated, the selected AEAD cipher authenticates itself
+    Implicit,
+    /// No algorithm both sides support
+    NoCommon,
+}
+
+/// Outcome of negotiating a single client profile against a server.
+/// `selected` follows the order of [`Algos::KEYS`].
+#[derive(Debug, Clone)]
+pub struct Negotiated {
+    pub profile: String,
+    pub selected: [Selected; 5],
+}
+
+impl Negotiated {
+    #[must_use]
+    pub fn is_compatible(&self) -> bool {
+        !self.selected.contains(&Selected::NoCommon)
+    }
+}
+
+/// Ciphers with built-in integrity, for which OpenSSH skips MAC selection
+#[must_use]
+pub fn is_aead(cipher: &str) -> bool {
+    matches!(cipher, "chacha20-poly1305@openssh.com" | "aes128-gcm@openssh.com" | "aes256-gcm@openssh.com")
+}
+
+struct BuiltinProfile {
+    name: &'static str,
+    lists: [&'static [&'static str]; 5],
+}
+
+const BUILTIN_PROFILES: &[BuiltinProfile] = &[
+    BuiltinProfile {
+        name: "OpenSSH 9.x",
+        lists: [
+            &["sntrup761x25519-sha512@openssh.com", "curve25519-sha256", "curve25519-sha256@libssh.org", "ecdh-sha2-nistp256", "ecdh-sha2-nistp384", "ecdh-sha2-nistp521", "diffie-hellman-group-exchange-sha256", "diffie-hellman-group16-sha512", "diffie-hellman-group18-sha512", "diffie-hellman-group14-sha256"],
+            &["ssh-ed25519", "ecdsa-sha2-nistp256", "ecdsa-sha2-nistp384", "ecdsa-sha2-nistp521", "sk-ssh-ed25519@openssh.com", "sk-ecdsa-sha2-nistp256@openssh.com", "rsa-sha2-512", "rsa-sha2-256"],
+            &["chacha20-poly1305@openssh.com", "aes128-ctr", "aes192-ctr", "aes256-ctr", "aes128-gcm@openssh.com", "aes256-gcm@openssh.com"],
+            &["umac-64-etm@openssh.com", "umac-128-etm@openssh.com", "hmac-sha2-256-etm@openssh.com", "hmac-sha2-512-etm@openssh.com", "hmac-sha1-etm@openssh.com", "umac-64@openssh.com", "umac-128@openssh.com", "hmac-sha2-256", "hmac-sha2-512", "hmac-sha1"],
+            &["none", "zlib@openssh.com"],
+        ],
+    },
+    BuiltinProfile {
+        name: "PuTTY 0.8x",
+        lists: [
+            &["sntrup761x25519-sha512@openssh.com", "curve25519-sha256", "curve25519-sha256@libssh.org", "ecdh-sha2-nistp256", "ecdh-sha2-nistp384", "ecdh-sha2-nistp521", "diffie-hellman-group18-sha512", "diffie-hellman-group17-sha512", "diffie-hellman-group16-sha512", "diffie-hellman-group15-sha512", "diffie-hellman-group-exchange-sha256", "diffie-hellman-group14-sha256", "diffie-hellman-group14-sha1", "rsa2048-sha256", "diffie-hellman-group-exchange-sha1", "diffie-hellman-group1-sha1", "rsa1024-sha1"],
+            &["ssh-ed448", "ssh-ed25519", "ecdsa-sha2-nistp256", "ecdsa-sha2-nistp384", "ecdsa-sha2-nistp521", "rsa-sha2-512", "rsa-sha2-256", "ssh-rsa", "ssh-dss"],
+            &["aes256-ctr", "aes256-cbc", "rijndael-cbc@lysator.liu.se", "aes192-ctr", "aes192-cbc", "aes128-ctr", "aes128-cbc", "chacha20-poly1305@openssh.com", "aes256-gcm@openssh.com", "aes128-gcm@openssh.com", "3des-ctr", "3des-cbc", "blowfish-ctr", "blowfish-cbc", "arcfour256", "arcfour128"],
+            &["hmac-sha2-256", "hmac-sha2-512", "hmac-sha1", "hmac-sha1-96", "hmac-md5", "hmac-sha2-256-etm@openssh.com", "hmac-sha2-512-etm@openssh.com", "hmac-sha1-etm@openssh.com", "hmac-sha1-96-etm@openssh.com", "hmac-md5-etm@openssh.com"],
+            &["none", "zlib", "zlib@openssh.com"],
+        ],
+    },
+    BuiltinProfile {
+        name: "Paramiko 3.x",
+        lists: [
+            &["curve25519-sha256@libssh.org", "ecdh-sha2-nistp256", "ecdh-sha2-nistp384", "ecdh-sha2-nistp521", "diffie-hellman-group16-sha512", "diffie-hellman-group-exchange-sha256", "diffie-hellman-group14-sha256", "diffie-hellman-group-exchange-sha1", "diffie-hellman-group14-sha1", "diffie-hellman-group1-sha1"],
+            &["ssh-ed25519", "ecdsa-sha2-nistp256", "ecdsa-sha2-nistp384", "ecdsa-sha2-nistp521", "rsa-sha2-512", "rsa-sha2-256", "ssh-rsa", "ssh-dss"],
+            &["aes128-ctr", "aes192-ctr", "aes256-ctr", "aes128-cbc", "aes192-cbc", "aes256-cbc", "3des-cbc"],
+            &["hmac-sha2-256", "hmac-sha2-512", "hmac-sha2-256-etm@openssh.com", "hmac-sha2-512-etm@openssh.com", "hmac-sha1", "hmac-md5", "hmac-sha1-96", "hmac-md5-96"],
+            &["none"],
+        ],
+    },
+    BuiltinProfile {
+        name: "libssh2 1.11",
+        lists: [
+            &["curve25519-sha256", "curve25519-sha256@libssh.org", "ecdh-sha2-nistp256", "ecdh-sha2-nistp384", "ecdh-sha2-nistp521", "diffie-hellman-group-exchange-sha256", "diffie-hellman-group16-sha512", "diffie-hellman-group18-sha512", "diffie-hellman-group14-sha256", "diffie-hellman-group14-sha1", "diffie-hellman-group1-sha1", "diffie-hellman-group-exchange-sha1"],
+            &["ssh-ed25519", "ecdsa-sha2-nistp256", "ecdsa-sha2-nistp384", "ecdsa-sha2-nistp521", "rsa-sha2-512", "rsa-sha2-256", "ssh-rsa", "ssh-dss"],
+            &["aes128-gcm@openssh.com", "aes256-gcm@openssh.com", "aes128-ctr", "aes192-ctr", "aes256-ctr", "aes256-cbc", "rijndael-cbc@lysator.liu.se", "aes192-cbc", "aes128-cbc", "blowfish-cbc", "arcfour128", "arcfour", "cast128-cbc", "3des-cbc"],
+            &["hmac-sha2-256-etm@openssh.com", "hmac-sha2-512-etm@openssh.com", "hmac-sha1-etm@openssh.com", "hmac-sha2-256", "hmac-sha2-512", "hmac-sha1", "hmac-sha1-96", "hmac-md5", "hmac-md5-96", "hmac-ripemd160", "hmac-ripemd160@openssh.com"],
+            &["none"],
+        ],
+    },
+    BuiltinProfile {
+        name: "Cisco IOS 15",
+        lists: [
+            &["diffie-hellman-group14-sha1", "diffie-hellman-group-exchange-sha1", "diffie-hellman-group1-sha1"],
+            &["ssh-rsa"],
+            &["aes128-ctr", "aes192-ctr", "aes256-ctr", "aes128-cbc", "3des-cbc", "aes192-cbc", "aes256-cbc"],
+            &["hmac-sha1", "hmac-sha1-96", "hmac-md5", "hmac-md5-96"],
+            &["none"],
+        ],
+    },
+];
+
+/// The client profiles shipped with sshscan
+#[must_use]
+pub fn builtin_profiles() -> Vec<ClientProfile> {
+    BUILTIN_PROFILES
+        .iter()
+        .map(|builtin| {
+            let mut algos = Algos::default();
+            for (key, list) in Algos::KEYS.into_iter().zip(builtin.lists) {
+                algos[key] = list.iter().map(|s| (*s).to_owned()).collect();
+            }
+            ClientProfile { name: builtin.name.to_owned(), algos }
+        })
+        .collect()
+}
+
+/// Profile file keys, in the order of [`Algos::KEYS`]
+const PROFILE_KEYS: [&str; 5] = ["kex", "host_key", "encryption", "mac", "compression"];
+
+/// Parse custom client profiles.
+///
+/// The format is INI-like: each `[name]` section starts a profile, followed by
+/// `key = algo1,algo2,...` lines where `key` is one of `kex`, `host_key`,
+/// `encryption`, `mac` or `compression`. Blank lines and lines starting with `#` are ignored.
+/// # Errors
+/// Returns error on unknown keys, lines outside of a section or profiles missing one of the keys
+pub fn parse_profiles(data: &str) -> Result<Vec<ClientProfile>, SshScanErr> {
+    let mut res: Vec<ClientProfile> = Vec::new();
+    for (lineno, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |why: &str| SshScanErr::InvalidProfile(format!("line {}: {why}", lineno + 1));
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            res.push(ClientProfile { name: name.trim().to_owned(), algos: Algos::default() });
+            continue;
+        }
+        let Some(profile) = res.last_mut() else {
+            return Err(err("expected [profile name] before algorithm lists"));
+        };
+        let Some((key, list)) = line.split_once('=') else {
+            return Err(err("expected key = algo1,algo2,..."));
+        };
+        let Some(key) = PROFILE_KEYS.iter().position(|k| *k == key.trim()).map(|i| Algos::KEYS[i]) else {
+            return Err(err("unknown key, expected one of kex, host_key, encryption, mac, compression"));
+        };
+        profile.algos[key] = list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned).collect();
+    }
+    // A missing list would negotiate to "no common algorithm" for that category
+    for profile in &res {
+        if let Some((key, _)) = PROFILE_KEYS.iter().zip(Algos::KEYS).find(|(_, category)| profile.algos[*category].is_empty()) {
+            return Err(SshScanErr::InvalidProfile(format!("profile [{}] has no {key} list", profile.name)));
+        }
+    }
+    Ok(res)
+}
+
+/// Predict what the SSH algorithm negotiation (RFC 4253 section 7.1) selects:
+/// the first algorithm on the client's list that the server also supports.
+/// Like OpenSSH, no MAC is selected when the cipher is an AEAD cipher.
+#[must_use]
+pub fn negotiate(server: &Algos, client: &ClientProfile) -> Negotiated {
+    let pick = |key: &str| {
+        client.algos[key]
+            .iter()
+            .find(|algo| server[key].contains(algo))
+            .map_or(Selected::NoCommon, |algo| Selected::Algorithm(algo.clone()))
+    };
+    let aead = matches!(pick("encryption_algorithms"), Selected::Algorithm(cipher) if is_aead(&cipher));
+    let selected = Algos::KEYS.map(|key| if aead && key == "mac_algorithms" { Selected::Implicit } else { pick(key) });
+    Negotiated { profile: client.name.clone(), selected }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn profile(lists: [&str; 5]) -> ClientProfile {
+        ClientProfile { name: "test".to_owned(), algos: fixtures::algos(lists) }
+    }
+
+    fn algorithm(name: &str) -> Selected {
+        Selected::Algorithm(name.to_owned())
+    }
+
+    #[test]
+    fn first_client_algorithm_the_server_supports_wins() {
+        let server = fixtures::algos(["ecdh-sha2-nistp256,curve25519-sha256", "ssh-ed25519", "aes128-ctr,aes256-ctr", "hmac-sha2-256,hmac-sha1", "none"]);
+        let client = profile(["sntrup761x25519-sha512@openssh.com,curve25519-sha256,ecdh-sha2-nistp256", "ssh-ed25519", "aes256-ctr,aes128-ctr", "hmac-sha1,hmac-sha2-256", "zlib,none"]);
+        let res = negotiate(&server, &client);
+        assert_eq!(res.selected, [algorithm("curve25519-sha256"), algorithm("ssh-ed25519"), algorithm("aes256-ctr"), algorithm("hmac-sha1"), algorithm("none")]);
+        assert!(res.is_compatible());
+    }
+
+    #[test]
+    fn reports_no_common_algorithm() {
+        let server = fixtures::algos(["diffie-hellman-group1-sha1", "ssh-dss", "3des-cbc", "hmac-md5", "none"]);
+        let client = profile(["curve25519-sha256", "ssh-ed25519", "aes128-ctr", "hmac-md5", "none"]);
+        let res = negotiate(&server, &client);
+        assert_eq!(res.selected[0], Selected::NoCommon);
+        assert_eq!(res.selected[3], algorithm("hmac-md5"));
+        assert!(!res.is_compatible());
+    }
+
+    #[test]
+    fn aead_cipher_skips_mac() {
+        let server = fixtures::algos(["curve25519-sha256", "ssh-ed25519", "aes256-gcm@openssh.com,aes128-ctr", "hmac-sha2-256", "none"]);
+        let client = profile(["curve25519-sha256", "ssh-ed25519", "aes256-gcm@openssh.com,aes128-ctr", "umac-64@openssh.com", "none"]);
+        let res = negotiate(&server, &client);
+        assert_eq!(res.selected[2], algorithm("aes256-gcm@openssh.com"));
+        // Without an AEAD cipher the MAC lists share nothing
+        assert_eq!(res.selected[3], Selected::Implicit);
+        assert!(res.is_compatible());
+    }
+
+    #[test]
+    fn parses_profiles() {
+        let data = "# comment\n[Old client]\nkex = diffie-hellman-group14-sha1\nhost_key = ssh-rsa\nencryption = aes128-cbc, aes128-ctr\nmac = hmac-sha1\ncompression = none\n";
+        let profiles = parse_profiles(data).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "Old client");
+        assert_eq!(profiles[0].algos["encryption_algorithms"], ["aes128-cbc", "aes128-ctr"]);
+    }
+
+    #[test]
+    fn rejects_incomplete_profiles() {
+        let err = parse_profiles("[Partial]\nkex = curve25519-sha256\nhost_key = ssh-ed25519\nencryption = aes128-ctr\ncompression = none\n").unwrap_err();
+        assert!(err.to_string().contains("profile [Partial] has no mac list"), "{err}");
+        assert!(parse_profiles("kex = curve25519-sha256\n").is_err());
+        assert!(parse_profiles("[Bad]\ncipher = aes128-ctr\n").is_err());
+    }
+
+    #[test]
+    fn builtin_profiles_are_complete() {
+        for profile in builtin_profiles() {
+            assert!(Algos::KEYS.iter().all(|key| !profile.algos[*key].is_empty()), "{}", profile.name);
+        }
+    }
+}
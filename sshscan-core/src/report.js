@@ -0,0 +1,143 @@
+"use strict";
+// Progressive enhancement for the sshscan report: filtering, sorting, collapsing and search.
+// The report stays fully usable without JavaScript, everything here only hides or reorders
+// elements that are already in the page.
+document.addEventListener("DOMContentLoaded", function () {
+  var hosts = Array.prototype.slice.call(document.querySelectorAll(".sshscan-htable-inner"));
+  var algoEntries = Array.prototype.slice.call(document.querySelectorAll(".sshscan-alist-inner"));
+  // Site-mode index pages have no host section, only the algorithm and product lists
+  var hostsSection = document.getElementById("sshscan-hosts");
+  var anchor = hostsSection || document.querySelector(".sshscan-alist-outer");
+  if (!anchor) { return; }
+
+  function el(tag, attrs, text) {
+    var e = document.createElement(tag);
+    for (var k in attrs) { e.setAttribute(k, attrs[k]); }
+    if (text) { e.textContent = text; }
+    return e;
+  }
+
+  function ipv4ToInt(addr) {
+    var parts = addr.split(".");
+    if (parts.length !== 4) { return null; }
+    var n = 0;
+    for (var i = 0; i < 4; i++) {
+      var p = Number(parts[i]);
+      if (!/^\d+$/.test(parts[i]) || p > 255) { return null; }
+      n = n * 256 + p;
+    }
+    return n;
+  }
+
+  // Accepts X.X.X.X/N for IPv4, anything else is treated as an address prefix (handy for IPv6)
+  function subnetMatcher(spec) {
+    var slash = spec.indexOf("/");
+    if (slash !== -1) {
+      var base = ipv4ToInt(spec.slice(0, slash));
+      var bits = Number(spec.slice(slash + 1));
+      if (base !== null && bits >= 0 && bits <= 32) {
+        var size = Math.pow(2, 32 - bits);
+        var start = base - (base % size);
+        return function (addr) {
+          var n = ipv4ToInt(addr);
+          return n !== null && n >= start && n < start + size;
+        };
+      }
+    }
+    return function (addr) { return addr.indexOf(spec) === 0; };
+  }
+
+  var controls = el("div", { id: "sshscan-controls" });
+  var search = el("input", { type: "search", placeholder: "Search everything" });
+  var subnet = el("input", { type: "text", placeholder: "Subnet (10.0.0.0/24)" });
+  var product = el("input", { type: "text", placeholder: "Product" });
+  var algo = el("input", { type: "text", placeholder: "Algorithm" });
+  var sort = el("select", {});
+  [["", "Sort algorithms: report order"], ["count-desc", "Count (most first)"], ["count-asc", "Count (fewest first)"],
+   ["first-desc", "First choice (most first)"], ["name", "Name"]].forEach(function (o) {
+    sort.appendChild(el("option", { value: o[0] }, o[1]));
+  });
+  var collapseAll = el("button", { type: "button" }, "Collapse all");
+  var expandAll = el("button", { type: "button" }, "Expand all");
+  var status = el("span", { class: "header-count" });
+  var hostControls = hostsSection ? [subnet, product] : [];
+  [search].concat(hostControls, [algo, sort, collapseAll, expandAll, status]).forEach(function (c) { controls.appendChild(c); });
+  anchor.parentNode.insertBefore(controls, anchor);
+
+  function applyFilters() {
+    var q = search.value.trim().toLowerCase();
+    var inSubnet = subnet.value.trim() ? subnetMatcher(subnet.value.trim()) : null;
+    var prod = product.value.trim().toLowerCase();
+    var alg = algo.value.trim().toLowerCase();
+    var shown = 0;
+    hosts.forEach(function (h) {
+      var addr = h.getAttribute("data-addr") || "";
+      var hprod = (h.getAttribute("data-product") || "").toLowerCase();
+      var halgos = (h.getAttribute("data-algos") || "").toLowerCase();
+      var ok = (!inSubnet || inSubnet(addr)) &&
+        (!prod || hprod.indexOf(prod) !== -1) &&
+        (!alg || halgos.split(" ").some(function (a) { return a.indexOf(alg) !== -1; })) &&
+        (!q || (addr + " " + hprod + " " + halgos).toLowerCase().indexOf(q) !== -1);
+      h.style.display = ok ? "" : "none";
+      if (ok) { shown++; }
+    });
+    Array.prototype.forEach.call(document.querySelectorAll(".sshscan-htable-outer"), function (outer) {
+      var any = Array.prototype.some.call(outer.querySelectorAll(".sshscan-htable-inner"), function (h) { return h.style.display !== "none"; });
+      outer.style.display = any ? "" : "none";
+    });
+    algoEntries.forEach(function (a) {
+      var name = (a.getAttribute("data-name") || "").toLowerCase();
+      var ok = (!alg || name.indexOf(alg) !== -1) && (!q || a.textContent.toLowerCase().indexOf(q) !== -1);
+      a.style.display = ok ? "" : "none";
+    });
+    if (hostsSection) { status.textContent = "Showing " + shown + " of " + hosts.length + " host:ports"; }
+  }
+
+  function num(e, attr) { return Number(e.getAttribute(attr) || 0); }
+  var originalOrder = new Map();
+  algoEntries.forEach(function (a, i) { originalOrder.set(a, i); });
+  function applySort() {
+    var how = sort.value;
+    Array.prototype.forEach.call(document.querySelectorAll(".sshscan-alist-outer"), function (outer) {
+      var entries = Array.prototype.slice.call(outer.querySelectorAll(".sshscan-alist-inner"));
+      entries.sort(function (a, b) {
+        switch (how) {
+          case "count-desc": return num(b, "data-count") - num(a, "data-count");
+          case "count-asc": return num(a, "data-count") - num(b, "data-count");
+          case "first-desc": return num(b, "data-first") - num(a, "data-first");
+          case "name": return (a.getAttribute("data-name") || "").localeCompare(b.getAttribute("data-name") || "");
+          default: return originalOrder.get(a) - originalOrder.get(b);
+        }
+      });
+      entries.forEach(function (e) { outer.appendChild(e); });
+    });
+  }
+
+  // Any section header toggles the visibility of the rest of its section
+  var collapsible = Array.prototype.slice.call(document.querySelectorAll(
+    "#sshscan-hosts > h2, .sshscan-htable-inner > h3, .sshscan-alist-outer > h2, .sshscan-alist-inner > h3"));
+  collapsible.forEach(function (header) {
+    header.classList.add("sshscan-collapsible");
+    header.addEventListener("click", function (ev) {
+      if (ev.target.tagName === "A") { return; }
+      header.parentNode.classList.toggle("sshscan-collapsed");
+    });
+  });
+  collapseAll.addEventListener("click", function () {
+    collapsible.forEach(function (h) { h.parentNode.classList.add("sshscan-collapsed"); });
+  });
+  expandAll.addEventListener("click", function () {
+    collapsible.forEach(function (h) { h.parentNode.classList.remove("sshscan-collapsed"); });
+  });
+  // Following an internal link into a collapsed section should reveal it
+  window.addEventListener("hashchange", function () {
+    var target = document.getElementById(decodeURIComponent(location.hash.slice(1)));
+    for (var n = target; n; n = n.parentNode) {
+      if (n.classList) { n.classList.remove("sshscan-collapsed"); }
+    }
+  });
+
+  [search, subnet, product, algo].forEach(function (i) { i.addEventListener("input", applyFilters); });
+  sort.addEventListener("change", applySort);
+  applyFilters();
+});
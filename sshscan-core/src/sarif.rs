@@ -0,0 +1,65 @@
+use serde_json::{Value, json};
+use crate::{
+    findings::{Finding, FindingKind},
+    html::{LINK, NAME},
+    host_port,
+};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Rule names in SARIF are conventionally PascalCase, e.g. "weak-kex" becomes "WeakKex"
+fn rule_name(kind: FindingKind) -> String {
+    kind.id()
+        .split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        })
+        .collect()
+}
+
+fn rule(kind: FindingKind) -> Value {
+    json!({
+        "id": kind.id(),
+        "name": rule_name(kind),
+        "shortDescription": { "text": kind.description() },
+        "defaultConfiguration": { "level": kind.default_severity().as_str() },
+    })
+}
+
+fn result(finding: &Finding) -> Value {
+    let host_port = host_port(&finding.addr, finding.portid);
+    json!({
+        "ruleId": finding.kind.id(),
+        "ruleIndex": FindingKind::ALL.iter().position(|k| *k == finding.kind),
+        "level": finding.severity.as_str(),
+        "message": { "text": finding.message },
+        "locations": [{
+            "physicalLocation": { "artifactLocation": { "uri": format!("ssh://{host_port}") } },
+            "logicalLocations": [{ "name": host_port, "kind": "host" }],
+        }],
+        "partialFingerprints": { "sshscanFinding/v1": format!("{}/{host_port}/{}", finding.kind.id(), finding.subject) },
+    })
+}
+
+/// Generate a SARIF 2.1.0 log with one rule per finding kind and one result per finding,
+/// so scans can be uploaded to code scanning dashboards.
+#[must_use]
+pub fn generate(findings: &[Finding]) -> String {
+    let log = json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": NAME,
+                    "informationUri": LINK,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": FindingKind::ALL.iter().map(|k| rule(*k)).collect::<Vec<_>>(),
+                }
+            },
+            "results": findings.iter().map(result).collect::<Vec<_>>(),
+        }],
+    });
+    serde_json::to_string_pretty(&log).unwrap_or_default()
+}
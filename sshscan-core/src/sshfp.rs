@@ -0,0 +1,100 @@
+use std::fmt::Write;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use crate::{host_port, Host, HostKey};
+
+/// SSHFP algorithm number of a key type (RFC 4255, 6594, 7479, 8709)
+fn algorithm(key_type: &str) -> Option<u8> {
+    match key_type {
+        "ssh-rsa" => Some(1),
+        "ssh-dss" => Some(2),
+        t if t.starts_with("ecdsa-sha2-") => Some(3),
+        "ssh-ed25519" => Some(4),
+        "ssh-ed448" => Some(6),
+        _ => None,
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::with_capacity(data.len() * 2), |mut acc, b| {
+        let _ = write!(acc, "{b:02x}");
+        acc
+    })
+}
+
+/// The SHA-1 (type 1) and SHA-256 (type 2) record data of a key,
+/// `None` for unknown key types and keys without the public key blob
+fn rdata(key: &HostKey) -> Option<[String; 2]> {
+    use base64::Engine;
+    let algorithm = algorithm(&key.key_type)?;
+    let blob = base64::engine::general_purpose::STANDARD.decode(key.key.as_deref()?).ok()?;
+    Some([
+        format!("{algorithm} 1 {}", hex(&Sha1::digest(&blob))),
+        format!("{algorithm} 2 {}", hex(&Sha256::digest(&blob))),
+    ])
+}
+
+/// Generate DNS SSHFP resource records for the host keys of every open host:port.
+/// Records are owned by the host names nmap resolved; hosts without one are listed as comments,
+/// as are host:ports on other ports than 22, since SSHFP cannot tell ports apart.
+#[must_use]
+pub fn generate(hosts: &[Host]) -> String {
+    let mut out = String::new();
+    for host in hosts {
+        for hp in host.port_states.iter().filter(|hp| hp.state) {
+            let records: Vec<String> = hp.host_keys.iter().filter_map(rdata).flatten().collect();
+            if records.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "; {}", host_port(&host.addr, hp.portid));
+            let comment = if host.hostnames.is_empty() || hp.portid != 22 { "; " } else { "" };
+            let owners: Vec<String> = if host.hostnames.is_empty() {
+                vec![host.addr.clone()]
+            } else {
+                host.hostnames.iter().map(|name| format!("{}.", name.trim_end_matches('.'))).collect()
+            };
+            for owner in &owners {
+                for record in &records {
+                    let _ = writeln!(out, "{comment}{owner} IN SSHFP {record}");
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    const KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+
+    fn host(addr: &str, portid: u16, hostnames: &[&str]) -> Host {
+        let mut port = fixtures::port(portid, fixtures::algos(["curve25519-sha256", "ssh-ed25519", "aes128-ctr", "hmac-sha2-256", "none"]));
+        port.host_keys.push(HostKey::from_blob("ssh-ed25519", KEY).unwrap());
+        port.host_keys.push(HostKey { key_type: "ssh-rsa".to_owned(), bits: Some(3072), fingerprint: "00".repeat(16), key: None });
+        let mut host = fixtures::host(addr, vec![port]);
+        host.hostnames = hostnames.iter().map(|name| (*name).to_owned()).collect();
+        host
+    }
+
+    #[test]
+    fn matches_ssh_keygen_records() {
+        // ssh-keygen -r bastion.example.com
+        assert_eq!(generate(&[host("10.0.0.1", 22, &["bastion.example.com"])]), "\
+; 10.0.0.1:22
+bastion.example.com. IN SSHFP 4 1 e9619e2ed56c2f2a71729db80bacc2ce9ccce8d4
+bastion.example.com. IN SSHFP 4 2 f83898df0bef57a4ee24985ba598ac17fccb0c0d333cc4af1dd92be14bc23aa5
+");
+    }
+
+    #[test]
+    fn comments_out_unnamed_hosts_and_other_ports() {
+        let out = generate(&[host("10.0.0.2", 22, &[]), host("10.0.0.3", 2222, &["alt.example.com."])]);
+        let records: Vec<&str> = out.lines().filter(|line| line.contains("SSHFP")).collect();
+        assert_eq!(records.len(), 4);
+        assert!(records[..2].iter().all(|line| line.starts_with("; 10.0.0.2 IN SSHFP 4 ")), "{out}");
+        assert!(records[2..].iter().all(|line| line.starts_with("; alt.example.com. IN SSHFP 4 ")), "{out}");
+    }
+}
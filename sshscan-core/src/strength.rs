@@ -0,0 +1,268 @@
+use crate::Algos;
+
+/// Rough security rating of a single SSH algorithm.
+///
+/// Ordering is meaningful: `Broken < Weak < Strong`. `Unknown` is used for
+/// algorithms we have no opinion on and for pseudo-algorithms such as
+/// `ext-info-s` or `kex-strict-s-v00@openssh.com`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Strength {
+    Unknown,
+    Broken,
+    Weak,
+    Strong,
+}
+
+impl Strength {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Broken => "broken",
+            Self::Weak => "weak",
+            Self::Strong => "strong",
+        }
+    }
+}
+
+impl core::fmt::Display for Strength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Classify an algorithm by name. `category` is one of [`Algos::KEYS`].
+#[must_use]
+pub fn classify(category: &str, algo: &str) -> Strength {
+    // Certificate variants share the rating of the underlying key type
+    let algo = algo.strip_suffix("-cert-v01@openssh.com").unwrap_or(algo);
+    match category {
+        "kex_algorithms" => match algo {
+            "diffie-hellman-group1-sha1"
+            | "diffie-hellman-group-exchange-sha1"
+            | "rsa1024-sha1" => Strength::Broken,
+            "diffie-hellman-group14-sha1"
+            | "ecdh-sha2-nistp256"
+            | "ecdh-sha2-nistp384"
+            | "ecdh-sha2-nistp521" => Strength::Weak,
+            "curve25519-sha256"
+            | "curve25519-sha256@libssh.org"
+            | "diffie-hellman-group14-sha256"
+            | "diffie-hellman-group16-sha512"
+            | "diffie-hellman-group18-sha512"
+            | "diffie-hellman-group-exchange-sha256"
+            | "sntrup761x25519-sha512"
+            | "sntrup761x25519-sha512@openssh.com"
+            | "mlkem768x25519-sha256" => Strength::Strong,
+            _ if algo.starts_with("gss-group1-sha1-") || algo.starts_with("gss-gex-sha1-") => Strength::Broken,
+            _ => Strength::Unknown,
+        },
+        "server_host_key_algorithms" => match algo {
+            "ssh-dss" | "x509v3-ssh-dss" => Strength::Broken,
+            "ssh-rsa"
+            | "x509v3-ssh-rsa"
+            | "ecdsa-sha2-nistp256"
+            | "ecdsa-sha2-nistp384"
+            | "ecdsa-sha2-nistp521" => Strength::Weak,
+            "rsa-sha2-256"
+            | "rsa-sha2-512"
+            | "ssh-ed25519"
+            | "sk-ssh-ed25519@openssh.com" => Strength::Strong,
+            _ => Strength::Unknown,
+        },
+        "encryption_algorithms" => match algo {
+            "none"
+            | "des-cbc"
+            | "3des-cbc"
+            | "blowfish-cbc"
+            | "cast128-cbc"
+            | "rijndael-cbc@lysator.liu.se" => Strength::Broken,
+            "aes128-cbc" | "aes192-cbc" | "aes256-cbc" => Strength::Weak,
+            "aes128-ctr"
+            | "aes192-ctr"
+            | "aes256-ctr"
+            | "aes128-gcm@openssh.com"
+            | "aes256-gcm@openssh.com"
+            | "chacha20-poly1305@openssh.com" => Strength::Strong,
+            _ if algo.starts_with("arcfour") => Strength::Broken,
+            _ => Strength::Unknown,
+        },
+        "mac_algorithms" => match algo {
+            "hmac-sha1"
+            | "hmac-sha1-etm@openssh.com"
+            | "umac-64@openssh.com"
+            | "umac-64-etm@openssh.com"
+            | "hmac-ripemd160"
+            | "hmac-ripemd160@openssh.com"
+            | "hmac-ripemd160-etm@openssh.com" => Strength::Weak,
+            "hmac-sha2-256"
+            | "hmac-sha2-512"
+            | "hmac-sha2-256-etm@openssh.com"
+            | "hmac-sha2-512-etm@openssh.com"
+            | "umac-128@openssh.com"
+            | "umac-128-etm@openssh.com" => Strength::Strong,
+            _ if algo == "none" || algo.starts_with("hmac-md5") || algo.starts_with("hmac-sha1-96") => Strength::Broken,
+            _ => Strength::Unknown,
+        },
+        "compression_algorithms" => match algo {
+            "zlib" => Strength::Weak,
+            "none" | "zlib@openssh.com" => Strength::Strong,
+            _ => Strength::Unknown,
+        },
+        _ => Strength::Unknown,
+    }
+}
+
+/// A weaker algorithm listed ahead of a stronger one in the server's preference order.
+#[derive(Debug, Clone)]
+pub struct PreferenceWarning {
+    pub category: &'static str,
+    pub preferred: String,
+    pub preferred_strength: Strength,
+    pub over: String,
+    pub over_strength: Strength,
+}
+
+impl core::fmt::Display for PreferenceWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}) is preferred over {} ({})", self.preferred, self.preferred_strength, self.over, self.over_strength)
+    }
+}
+
+/// Find algorithms the server prefers over a strictly stronger alternative it also offers.
+/// At most one warning is emitted per weak algorithm, naming the first stronger algorithm after it.
+#[must_use]
+pub fn preference_warnings(algos: &Algos) -> Vec<PreferenceWarning> {
+    let mut res = Vec::new();
+    for category in Algos::KEYS {
+        let rated: Vec<(&String, Strength)> = algos[category]
+            .iter()
+            .map(|algo| (algo, classify(category, algo)))
+            .filter(|(_, strength)| *strength != Strength::Unknown)
+            .collect();
+        for (i, (preferred, preferred_strength)) in rated.iter().enumerate() {
+            if let Some((over, over_strength)) = rated[i + 1..].iter().find(|(_, s)| s > preferred_strength) {
+                res.push(PreferenceWarning {
+                    category,
+                    preferred: (*preferred).clone(),
+                    preferred_strength: *preferred_strength,
+                    over: (*over).clone(),
+                    over_strength: *over_strength,
+                });
+            }
+        }
+    }
+    res
+}
+
+/// Overall letter grade of a single host:port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Grade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl Grade {
+    pub const ALL: [Self; 5] = [Self::A, Self::B, Self::C, Self::D, Self::F];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+            Self::D => "D",
+            Self::F => "F",
+        }
+    }
+}
+
+impl core::fmt::Display for Grade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Grade a host:port by the worst algorithm it offers, and whether that algorithm is a first choice:
+///
+/// * A: only strong (or unrated) algorithms
+/// * B: weak algorithms offered, but never preferred
+/// * C: a weak algorithm is the first choice in some category
+/// * D: broken algorithms offered, but never preferred
+/// * F: a broken algorithm is the first choice in some category
+///
+/// Returns `None` when no algorithms were enumerated.
+#[must_use]
+pub fn grade(algos: &Algos) -> Option<Grade> {
+    if algos.is_empty() {
+        return None;
+    }
+    let mut worst_offered = Strength::Strong;
+    let mut worst_preferred = Strength::Strong;
+    for category in Algos::KEYS {
+        // Only the server's actual first choice is preferred, even when it is unrated
+        for (rank, algo) in algos[category].iter().enumerate() {
+            let strength = classify(category, algo);
+            if strength == Strength::Unknown {
+                continue;
+            }
+            worst_offered = worst_offered.min(strength);
+            if rank == 0 {
+                worst_preferred = worst_preferred.min(strength);
+            }
+        }
+    }
+    Some(match (worst_offered, worst_preferred) {
+        (_, Strength::Broken) => Grade::F,
+        (Strength::Broken, _) => Grade::D,
+        (_, Strength::Weak) => Grade::C,
+        (Strength::Weak, _) => Grade::B,
+        _ => Grade::A,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn grade_of(kex: &str, encryption: &str) -> Option<Grade> {
+        grade(&fixtures::algos([kex, "ssh-ed25519", encryption, "hmac-sha2-256", "none"]))
+    }
+
+    #[test]
+    fn grades_by_worst_offered_and_preferred() {
+        assert_eq!(grade_of("curve25519-sha256", "aes128-ctr"), Some(Grade::A));
+        assert_eq!(grade_of("curve25519-sha256,diffie-hellman-group14-sha1", "aes128-ctr"), Some(Grade::B));
+        assert_eq!(grade_of("diffie-hellman-group14-sha1,curve25519-sha256", "aes128-ctr"), Some(Grade::C));
+        assert_eq!(grade_of("curve25519-sha256", "aes128-ctr,3des-cbc"), Some(Grade::D));
+        assert_eq!(grade_of("curve25519-sha256", "3des-cbc,aes128-ctr"), Some(Grade::F));
+        assert_eq!(grade(&crate::Algos::default()), None);
+    }
+
+    #[test]
+    fn unrated_first_choice_is_not_skipped() {
+        assert_eq!(grade_of("curve25519-sha256", "example-cipher@example.com,3des-cbc"), Some(Grade::D));
+        assert_eq!(grade_of("curve25519-sha256", "example-cipher@example.com"), Some(Grade::A));
+    }
+
+    #[test]
+    fn warns_about_weaker_preferences() {
+        let algos = fixtures::algos([
+            "diffie-hellman-group1-sha1,example-kex@example.com,diffie-hellman-group14-sha1,curve25519-sha256",
+            "ssh-ed25519,ssh-rsa",
+            "aes128-ctr",
+            "hmac-sha1",
+            "none",
+        ]);
+        let warnings: Vec<String> = preference_warnings(&algos).iter().map(ToString::to_string).collect();
+        assert_eq!(warnings, [
+            "diffie-hellman-group1-sha1 (broken) is preferred over diffie-hellman-group14-sha1 (weak)",
+            "diffie-hellman-group14-sha1 (weak) is preferred over curve25519-sha256 (strong)",
+        ]);
+        assert!(preference_warnings(&fixtures::algos(["curve25519-sha256", "ssh-ed25519", "aes128-ctr", "hmac-sha2-256", "none"])).is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::SshScanErr;
+
+/// Largest IPv6 network that is expanded into single addresses, a /116.
+/// Anything bigger, like a /64, is far too large to scan address by address.
+pub const MAX_IPV6_HOST_BITS: u8 = 12;
+
+/// A host or set of hosts to scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    /// A host name, resolved by nmap
+    Name(String),
+    Ip(IpAddr),
+    /// A network in CIDR notation
+    Network { addr: IpAddr, prefix: u8 },
+    /// An inclusive range of IPv4 addresses
+    Range { start: Ipv4Addr, end: Ipv4Addr },
+}
+
+impl Address {
+    /// Whether this is an IPv6 address or network, `None` for host names
+    #[must_use]
+    pub fn is_ipv6(&self) -> Option<bool> {
+        match self {
+            Self::Name(_) => None,
+            Self::Ip(addr) | Self::Network { addr, .. } => Some(addr.is_ipv6()),
+            Self::Range { .. } => Some(false),
+        }
+    }
+
+    /// The address as nmap target specifications. Ranges within one /24 use nmap's octet range
+    /// syntax, other ranges are split into the CIDR blocks covering them. IPv6 networks are
+    /// expanded into single addresses.
+    #[must_use]
+    pub fn nmap_specs(&self) -> Vec<String> {
+        match self {
+            Self::Network { addr: IpAddr::V6(addr), prefix } => {
+                let first = u128::from(*addr) & (u128::MAX.checked_shl(u32::from(128 - prefix)).unwrap_or(0));
+                (0..1u128 << (128 - prefix)).map(|i| Ipv6Addr::from(first + i).to_string()).collect()
+            }
+            Self::Range { start, end } if start.octets()[..3] == end.octets()[..3] => {
+                vec![format!("{start}-{}", end.octets()[3])]
+            }
+            Self::Range { start, end } => range_blocks(u32::from(*start), u32::from(*end))
+                .into_iter()
+                .map(|(addr, prefix)| format!("{}/{prefix}", Ipv4Addr::from(addr)))
+                .collect(),
+            other => vec![other.to_string()],
+        }
+    }
+
+    /// The address as nmap `--exclude` specifications. Unlike [`Self::nmap_specs`], IPv6 networks
+    /// stay in CIDR notation, they may be far too large to expand.
+    #[must_use]
+    pub fn exclude_specs(&self) -> Vec<String> {
+        match self {
+            Self::Network { .. } => vec![self.to_string()],
+            other => other.nmap_specs(),
+        }
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(name),
+            Self::Ip(addr) => addr.fmt(f),
+            Self::Network { addr, prefix } => write!(f, "{addr}/{prefix}"),
+            Self::Range { start, end } => write!(f, "{start}-{end}"),
+        }
+    }
+}
+
+/// A sorted, non-overlapping set of port ranges, written like nmap's `-p`: `22,2222,2200-2299`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ports(Vec<(u16, u16)>);
+
+impl Ports {
+    #[must_use]
+    pub fn single(port: u16) -> Self {
+        Self(vec![(port, port)])
+    }
+}
+
+impl std::fmt::Display for Ports {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (start, end)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            if start == end {
+                write!(f, "{start}")?;
+            } else {
+                write!(f, "{start}-{end}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Ports {
+    type Err = SshScanErr;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let err = || SshScanErr::InvalidArgument(format!("invalid ports '{text}': expected ports 1 to 65535 or ranges like 2200-2299"));
+        let port = |p: &str| p.trim().parse::<u16>().ok().filter(|p| *p != 0).ok_or_else(err);
+        let mut ranges = Vec::new();
+        for part in text.split(',') {
+            let range = match part.split_once('-') {
+                Some((start, end)) => (port(start)?, port(end)?),
+                None => (port(part)?, port(part)?),
+            };
+            if range.1 < range.0 {
+                return Err(err());
+            }
+            ranges.push(range);
+        }
+        ranges.sort_unstable();
+        let mut merged: Vec<(u16, u16)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if u32::from(start) <= u32::from(last.1) + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Ok(Self(merged))
+    }
+}
+
+/// An address with the ports to scan it on, `None` for the ports given on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub address: Address,
+    pub ports: Option<Ports>,
+}
+
+/// The CIDR blocks exactly covering `start..=end`
+fn range_blocks(mut start: u32, end: u32) -> Vec<(u32, u8)> {
+    let mut res = Vec::new();
+    loop {
+        // Largest block aligned at `start` that does not run past `end`
+        let mut size = if start == 0 { 32 } else { start.trailing_zeros() };
+        while size > 0 && u64::from(start) + (1u64 << size) - 1 > u64::from(end) {
+            size -= 1;
+        }
+        res.push((start, (32 - size) as u8));
+        let next = u64::from(start) + (1u64 << size);
+        if next > u64::from(end) {
+            return res;
+        }
+        start = next as u32;
+    }
+}
+
+/// Whether `name` is a syntactically valid DNS host name
+fn valid_host_name(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Parse an address, CIDR network, dash range (`10.0.0.1-50` or `10.0.0.1-10.0.1.20`) or host name
+/// # Errors
+/// When the text is none of these, e.g. an octet above 255 or a prefix longer than the address,
+/// or an IPv6 network larger than a /116
+pub fn parse_address(text: &str) -> Result<Address, SshScanErr> {
+    parse_address_limited(text, true)
+}
+
+/// Parse an address not to scan, like [`parse_address`] but accepting IPv6 networks of any size
+/// # Errors
+/// When the text is not an address, network, range or host name
+pub fn parse_exclusion(text: &str) -> Result<Address, SshScanErr> {
+    parse_address_limited(text, false)
+}
+
+fn parse_address_limited(text: &str, limit_ipv6: bool) -> Result<Address, SshScanErr> {
+    let err = |what: &str| SshScanErr::InvalidArgument(format!("invalid target '{text}': {what}"));
+    if let Ok(addr) = text.parse::<IpAddr>() {
+        return Ok(Address::Ip(addr));
+    }
+    if let Some((addr, prefix)) = text.split_once('/') {
+        let addr: IpAddr = addr.parse().map_err(|_| err("not an IP network"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix: u8 = prefix.parse().ok().filter(|p| *p <= max).ok_or_else(|| err(&format!("prefix must be 0 to {max}")))?;
+        if limit_ipv6 && addr.is_ipv6() && prefix < 128 - MAX_IPV6_HOST_BITS {
+            return Err(err(&format!(
+                "IPv6 networks larger than /{} cannot be scanned, a /{prefix} has 2^{} addresses",
+                128 - MAX_IPV6_HOST_BITS,
+                128 - prefix
+            )));
+        }
+        return Ok(Address::Network { addr, prefix });
+    }
+    if let Some((start, end)) = text.split_once('-')
+        && let Ok(start) = start.parse::<Ipv4Addr>()
+    {
+        let end = match end.parse::<u8>() {
+            Ok(last) => {
+                let [a, b, c, _] = start.octets();
+                Ipv4Addr::new(a, b, c, last)
+            }
+            Err(_) => end.parse().map_err(|_| err("range end must be an octet or IPv4 address"))?,
+        };
+        if end < start {
+            return Err(err("range end is before its start"));
+        }
+        return Ok(Address::Range { start, end });
+    }
+    // Names made only of numbers are mistyped addresses, not host names
+    if text.split('.').all(|label| label.chars().all(|c| c.is_ascii_digit())) {
+        return Err(err("not a valid IPv4 address"));
+    }
+    if !valid_host_name(text) {
+        return Err(err("not a valid address or host name"));
+    }
+    Ok(Address::Name(text.to_owned()))
+}
+
+/// Parse a target: an address as accepted by [`parse_address`], optionally followed by `:ports`,
+/// e.g. `10.0.0.5:22,2222`, `appliance.example.com:2200-2299` or `[2001:db8::1]:2222`
+/// # Errors
+/// When the address or ports are invalid
+pub fn parse_target(text: &str) -> Result<Target, SshScanErr> {
+    if let Some(rest) = text.strip_prefix('[') {
+        let (address, ports) = rest
+            .split_once(']')
+            .ok_or_else(|| SshScanErr::InvalidArgument(format!("invalid target '{text}': missing ']'")))?;
+        let ports = match ports.strip_prefix(':') {
+            Some(ports) => Some(ports.parse()?),
+            None if ports.is_empty() => None,
+            None => return Err(SshScanErr::InvalidArgument(format!("invalid target '{text}': expected ':' after ']'"))),
+        };
+        return Ok(Target { address: parse_address(address)?, ports });
+    }
+    // More than one colon is a bare IPv6 address, ports need the [addr]:port form
+    if text.matches(':').count() == 1
+        && let Some((address, ports)) = text.rsplit_once(':')
+    {
+        return Ok(Target { address: parse_address(address)?, ports: Some(ports.parse()?) });
+    }
+    Ok(Target { address: parse_address(text)?, ports: None })
+}
+
+/// Parse a targets file: one target per line, optionally with its own ports.
+/// `#` comments and blank lines are ignored.
+/// # Errors
+/// On the first invalid target, naming its line
+pub fn parse_file(data: &str) -> Result<Vec<Target>, SshScanErr> {
+    entries(data).map(|(line_no, entry)| parse_target(entry).map_err(|e| at_line(line_no, e))).collect()
+}
+
+/// Parse an exclusions file: one address per line, with `#` comments and blank lines ignored
+/// # Errors
+/// On the first invalid address, naming its line
+pub fn parse_exclude_file(data: &str) -> Result<Vec<Address>, SshScanErr> {
+    entries(data).map(|(line_no, entry)| parse_exclusion(entry).map_err(|e| at_line(line_no, e))).collect()
+}
+
+fn entries(data: &str) -> impl Iterator<Item = (usize, &str)> {
+    data.lines()
+        .enumerate()
+        .map(|(line_no, line)| (line_no + 1, line.split_once('#').map_or(line, |(entry, _)| entry).trim()))
+        .filter(|(_, entry)| !entry.is_empty())
+}
+
+fn at_line(line_no: usize, e: SshScanErr) -> SshScanErr {
+    match e {
+        SshScanErr::InvalidArgument(msg) => SshScanErr::InvalidArgument(format!("line {line_no}: {msg}")),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ipv6_targets() {
+        let loopback = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(parse_target("::1").unwrap(), Target { address: Address::Ip(loopback), ports: None });
+        assert_eq!(
+            parse_target("[::1]:2222").unwrap(),
+            Target { address: Address::Ip(loopback), ports: Some(Ports::single(2222)) },
+        );
+        assert_eq!(parse_target("[::1]").unwrap().ports, None);
+        assert!(parse_target("[::1]2222").is_err());
+    }
+
+    #[test]
+    fn bare_ipv6_is_not_split_on_colons() {
+        let target = parse_target("2001:db8::22").unwrap();
+        assert_eq!(target.address, Address::Ip("2001:db8::22".parse().unwrap()));
+        assert_eq!(target.ports, None);
+    }
+
+    #[test]
+    fn limits_ipv6_networks() {
+        assert!(parse_address("2001:db8::/64").is_err());
+        assert!(parse_address("2001:db8::/115").is_err());
+        let network = parse_address("2001:db8::/116").unwrap();
+        let specs = network.nmap_specs();
+        assert_eq!(specs.len(), 4096);
+        assert_eq!(specs.first().map(String::as_str), Some("2001:db8::"));
+        assert_eq!(specs.last().map(String::as_str), Some("2001:db8::fff"));
+    }
+
+    #[test]
+    fn ipv6_exclusions_are_not_limited() {
+        let network = parse_exclusion("2001:db8::/64").unwrap();
+        assert_eq!(network.exclude_specs(), ["2001:db8::/64"]);
+        assert!(parse_exclude_file("10.0.0.5\n2001:db8::/48 # lab\n").is_ok());
+        assert_eq!(parse_exclusion("10.0.0.1-3").unwrap().exclude_specs(), ["10.0.0.1-3"]);
+    }
+
+    #[test]
+    fn splits_dash_ranges() {
+        let range = parse_address("10.0.0.1-50").unwrap();
+        assert_eq!(range, Address::Range { start: Ipv4Addr::new(10, 0, 0, 1), end: Ipv4Addr::new(10, 0, 0, 50) });
+        assert_eq!(range.nmap_specs(), ["10.0.0.1-50"]);
+        let range = parse_address("10.0.0.250-10.0.2.1").unwrap();
+        assert_eq!(range.nmap_specs(), ["10.0.0.250/31", "10.0.0.252/30", "10.0.1.0/24", "10.0.2.0/31"]);
+        assert_eq!(range_blocks(0, u32::MAX), [(0, 0)]);
+        assert!(parse_address("10.0.0.50-10").is_err());
+        assert!(parse_address("10.0.0.1-10.0.0.256").is_err());
+    }
+
+    #[test]
+    fn rejects_numeric_names() {
+        assert!(parse_address("10.0.0.256").is_err());
+        assert!(parse_address("10.0.1").is_err());
+        assert!(parse_address("22").is_err());
+        assert!(parse_address("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn validates_host_names() {
+        assert_eq!(parse_address("bastion.example.com.").unwrap(), Address::Name("bastion.example.com.".to_owned()));
+        assert!(parse_address("host_1.example.com").is_ok());
+        assert!(parse_address("1password.example.com").is_ok());
+        assert!(parse_address("-bad.example.com").is_err());
+        assert!(parse_address("bad-.example.com").is_err());
+        assert!(parse_address("a..b").is_err());
+        assert!(parse_address("bad name").is_err());
+        assert!(parse_address(&format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn merges_port_lists() {
+        assert_eq!("22,23,20-21".parse::<Ports>().unwrap().to_string(), "20-23");
+        assert_eq!("2222, 22,2200-2299".parse::<Ports>().unwrap().to_string(), "22,2200-2299");
+        assert_eq!("22,22".parse::<Ports>().unwrap(), Ports::single(22));
+        assert!("23-22".parse::<Ports>().is_err());
+        assert!("0".parse::<Ports>().is_err());
+        assert!("0-22".parse::<Ports>().is_err());
+        assert!("65536".parse::<Ports>().is_err());
+        assert!("22,".parse::<Ports>().is_err());
+    }
+
+    #[test]
+    fn parses_target_files() {
+        let targets = parse_file("# servers\n\n10.0.0.5:22,2222\nbastion.example.com # jump host\n  [::1]:2200-2201  \n").unwrap();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0].ports, Some("22,2222".parse().unwrap()));
+        assert_eq!(targets[1], Target { address: Address::Name("bastion.example.com".to_owned()), ports: None });
+        assert_eq!(targets[2].ports.as_ref().map(ToString::to_string).as_deref(), Some("2200-2201"));
+        let err = parse_file("10.0.0.1\n# comment\n10.0.0.300\n").unwrap_err();
+        assert!(err.to_string().contains("line 3: invalid target '10.0.0.300'"), "{err}");
+        let err = parse_exclude_file("10.0.0.1\n10.0.0.1:22\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+}
@@ -0,0 +1,435 @@
+//! A minimal Handlebars-style template engine for custom report layouts.
+//!
+//! Supported syntax:
+//! * `{{ path.to.value }}` inserts a value, HTML escaped
+//! * `{{{ path.to.value }}}` inserts a value without escaping
+//! * `{{#each path}} ... {{/each}}` repeats the body for every item of a list (or value of a map),
+//!   inside the body `this` is the current item, `@index` its position and `@key` its key for maps
+//! * `{{#if path}} ... {{else}} ... {{/if}}` renders the first branch when the value is truthy
+//! * `{{! comment }}` is dropped from the output
+//!
+//! Names are looked up in the current item first, then in each enclosing scope up to the root.
+use std::{borrow::Cow, collections::BTreeMap};
+use crate::{
+    agg_data::{self, AggregatedData},
+    findings::Finding,
+    html::{HOST_HEADERS, ReportOptions},
+    strength::{self, preference_warnings},
+    anchor_id, host_port, Algos, Host, ScanInfo, SshScanErr,
+};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Num(i64),
+    Str(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Self::Null => false,
+            Self::Bool(b) => *b,
+            Self::Num(n) => *n != 0,
+            Self::Str(s) => !s.is_empty(),
+            Self::List(l) => !l.is_empty(),
+            Self::Map(m) => !m.is_empty(),
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        match self {
+            Self::Null | Self::List(_) | Self::Map(_) => {},
+            Self::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Self::Num(n) => out.push_str(&n.to_string()),
+            Self::Str(s) => out.push_str(s),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Map(m) => m.get(key),
+            Self::List(l) => key.parse::<usize>().ok().and_then(|i| l.get(i)),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Str(value.to_owned())
+    }
+}
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::Num(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+impl From<u16> for Value {
+    fn from(value: u16) -> Self {
+        Self::Num(value.into())
+    }
+}
+impl<T: Into<Self>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+impl<T: Into<Self>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Self::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+macro_rules! map {
+    ($($key:expr => $val:expr),* $(,)?) => {
+        Value::Map(BTreeMap::from([$(($key.to_owned(), Value::from($val))),*]))
+    };
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var { path: String, escape: bool },
+    Each { path: String, body: Vec<Node> },
+    If { path: String, then: Vec<Node>, otherwise: Vec<Node> },
+}
+
+/// A starter template: a simplified default report using all the data available to templates
+pub const DEFAULT: &str = include_str!("template.html");
+
+/// A parsed template, ready to be rendered any number of times
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Block {
+    Each(String),
+    If(String, Option<Vec<Node>>),
+}
+
+impl Template {
+    /// Parse template source.
+    /// # Errors
+    /// Returns error on unterminated tags and unbalanced or unknown block tags
+    pub fn parse(src: &str) -> Result<Self, SshScanErr> {
+        let err = |offset: usize, why: &str| {
+            let line = src[..offset].matches('\n').count() + 1;
+            SshScanErr::Template(format!("line {line}: {why}"))
+        };
+        // Stack of open blocks, each with the nodes collected so far in the enclosing scope
+        let mut stack: Vec<(Block, Vec<Node>, usize)> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut rest = src;
+        while let Some(start) = rest.find("{{") {
+            let offset = src.len() - rest.len() + start;
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_owned()));
+            }
+            let (raw, close) = if rest[start..].starts_with("{{{") { (true, "}}}") } else { (false, "}}") };
+            let open_len = if raw { 3 } else { 2 };
+            let tag_start = start + open_len;
+            let Some(len) = rest[tag_start..].find(close) else {
+                return Err(err(offset, "unterminated tag"));
+            };
+            let tag = rest[tag_start..tag_start + len].trim();
+            rest = &rest[tag_start + len + close.len()..];
+            if raw {
+                nodes.push(Node::Var { path: tag.to_owned(), escape: false });
+            } else if tag.starts_with('!') {
+                // comment
+            } else if let Some(path) = helper_arg(tag, "#each") {
+                stack.push((Block::Each(path.to_owned()), std::mem::take(&mut nodes), offset));
+            } else if let Some(path) = helper_arg(tag, "#if") {
+                stack.push((Block::If(path.to_owned(), None), std::mem::take(&mut nodes), offset));
+            } else if tag == "else" {
+                match stack.last_mut() {
+                    Some((Block::If(_, then @ None), _, _)) => *then = Some(std::mem::take(&mut nodes)),
+                    _ => return Err(err(offset, "{{else}} outside of {{#if}}")),
+                }
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let Some((block, parent, _)) = stack.pop() else {
+                    return Err(err(offset, "closing tag without an open block"));
+                };
+                let body = std::mem::replace(&mut nodes, parent);
+                let node = match (block, name.trim()) {
+                    (Block::Each(path), "each") => Node::Each { path, body },
+                    (Block::If(path, None), "if") => Node::If { path, then: body, otherwise: Vec::new() },
+                    (Block::If(path, Some(then)), "if") => Node::If { path, then, otherwise: body },
+                    _ => return Err(err(offset, "mismatched closing tag")),
+                };
+                nodes.push(node);
+            } else if tag.starts_with('#') {
+                return Err(err(offset, "unknown block helper"));
+            } else {
+                nodes.push(Node::Var { path: tag.to_owned(), escape: true });
+            }
+        }
+        if let Some((_, _, offset)) = stack.last() {
+            return Err(err(*offset, "block is never closed"));
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_owned()));
+        }
+        Ok(Self { nodes })
+    }
+
+    #[must_use]
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &[Scope { value: context, index: None, key: None }], &mut out);
+        out
+    }
+}
+
+/// The argument of a block helper tag, which must be separated from the helper name by whitespace
+fn helper_arg<'t>(tag: &'t str, helper: &str) -> Option<&'t str> {
+    tag.strip_prefix(helper).filter(|arg| arg.starts_with(char::is_whitespace)).map(str::trim)
+}
+
+#[derive(Clone, Copy)]
+struct Scope<'v> {
+    value: &'v Value,
+    index: Option<usize>,
+    key: Option<&'v str>,
+}
+
+fn lookup<'v>(scopes: &[Scope<'v>], path: &str) -> Option<Cow<'v, Value>> {
+    let current = scopes.last()?;
+    match path {
+        "this" | "." => return Some(Cow::Borrowed(current.value)),
+        "@index" => return current.index.map(|i| Cow::Owned(Value::from(i))),
+        "@key" => return current.key.map(|k| Cow::Owned(Value::from(k))),
+        _ => {},
+    }
+    let mut parts = path.split('.');
+    let mut found = match parts.next()? {
+        "this" => Some(current.value),
+        first => scopes.iter().rev().find_map(|scope| scope.value.get(first)),
+    };
+    for part in parts {
+        found = found?.get(part);
+    }
+    found.map(Cow::Borrowed)
+}
+
+fn render_nodes(nodes: &[Node], scopes: &[Scope<'_>], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { path, escape } => {
+                let Some(value) = lookup(scopes, path) else { continue };
+                if *escape {
+                    let mut raw = String::new();
+                    value.render(&mut raw);
+                    out.push_str(&build_html::escape_html(&raw));
+                } else {
+                    value.render(out);
+                }
+            },
+            Node::If { path, then, otherwise } => {
+                let branch = if lookup(scopes, path).is_some_and(|v| v.truthy()) { then } else { otherwise };
+                render_nodes(branch, scopes, out);
+            },
+            Node::Each { path, body } => {
+                let Some(Cow::Borrowed(list)) = lookup(scopes, path) else { continue };
+                let items: Vec<(Option<&str>, &Value)> = match list {
+                    Value::List(l) => l.iter().map(|v| (None, v)).collect(),
+                    Value::Map(m) => m.iter().map(|(k, v)| (Some(k.as_str()), v)).collect(),
+                    _ => continue,
+                };
+                let mut inner_scopes = scopes.to_vec();
+                for (index, (key, value)) in items.into_iter().enumerate() {
+                    inner_scopes.push(Scope { value, index: Some(index), key });
+                    render_nodes(body, &inner_scopes, out);
+                    inner_scopes.pop();
+                }
+            },
+        }
+    }
+}
+
+fn format_timestamp(ts: Option<i64>, options: &ReportOptions) -> Value {
+    ts.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|t| options.format_time(t))
+        .into()
+}
+
+fn algos_value(algos: &Algos) -> Value {
+    map! {
+        "kex" => algos["kex_algorithms"].clone(),
+        "host_key" => algos["server_host_key_algorithms"].clone(),
+        "encryption" => algos["encryption_algorithms"].clone(),
+        "mac" => algos["mac_algorithms"].clone(),
+        "compression" => algos["compression_algorithms"].clone(),
+    }
+}
+
+/// Build the data available to templates.
+///
+/// Top level names: `title`, `subtitle`, `footer`, `logo`, `extra_css`, `generated`,
+/// `style` (the default CSS), `meta` (nmap run metadata), `hosts`, `categories`
+/// (aggregated algorithm lists) and `findings`.
+#[must_use]
+pub fn build_context(hosts: &[Host], agg_data: &AggregatedData, info: &ScanInfo, findings: &[Finding], options: &ReportOptions) -> Value {
+    let hosts_value: Vec<Value> = hosts
+        .iter()
+        .map(|host| {
+            let ports: Vec<Value> = host
+                .port_states
+                .iter()
+                .map(|hp| map! {
+                    "id" => host_port(&host.addr, hp.portid),
+                    "anchor" => anchor_id(&host.addr, hp.portid),
+                    "port" => hp.portid,
+                    "open" => hp.state,
+                    "product" => hp.product.clone(),
+                    "hassh" => hp.hassh.clone(),
+                    "grade" => strength::grade(&hp.algos).map(|g| g.as_str()),
+                    "algos" => algos_value(&hp.algos),
+                    "warnings" => preference_warnings(&hp.algos).iter().map(ToString::to_string).collect::<Vec<_>>(),
+                })
+                .collect();
+            map! { "addr" => host.addr.as_str(), "status" => host.status.as_str(), "ports" => ports }
+        })
+        .collect();
+    let categories: Vec<Value> = HOST_HEADERS
+        .iter()
+        .map(|header| {
+            let algorithms: Vec<Value> = agg_data::by_count(&agg_data[header.data_key])
+                .into_iter()
+                .map(|(name, offers)| {
+                    let offers: Vec<Value> = offers
+                        .iter()
+                        .map(|o| map! {
+                            "id" => host_port(&o.host.addr, o.portid),
+                            "anchor" => anchor_id(&o.host.addr, o.portid),
+                            "addr" => o.host.addr.as_str(),
+                            "port" => o.portid,
+                            "rank" => o.rank + 1,
+                        })
+                        .collect();
+                    map! {
+                        "name" => name.as_str(),
+                        "strength" => strength::classify(header.algo_key, name).as_str(),
+                        "count" => offers.len(),
+                        "first_choice" => agg_data::first_choice_count(&agg_data[header.data_key][name]),
+                        "offers" => offers,
+                    }
+                })
+                .collect();
+            map! { "title" => header.title, "key" => header.data_key, "algorithms" => algorithms }
+        })
+        .collect();
+    let findings: Vec<Value> = findings
+        .iter()
+        .map(|f| map! {
+            "id" => host_port(&f.addr, f.portid),
+            "anchor" => anchor_id(&f.addr, f.portid),
+            "addr" => f.addr.as_str(),
+            "port" => f.portid,
+            "kind" => f.kind.id(),
+            "severity" => f.severity.as_str(),
+            "subject" => f.subject.as_str(),
+            "message" => f.message.as_str(),
+        })
+        .collect();
+    map! {
+        "title" => options.page_title(),
+        "subtitle" => options.subtitle.clone(),
+        "footer" => options.footer.clone(),
+        "logo" => options.logo.clone(),
+        "extra_css" => options.extra_css.clone(),
+        "generated" => options.now(),
+        "style" => crate::html::STYLE,
+        "meta" => map! {
+            "args" => info.args.clone(),
+            "nmap_version" => info.nmap_version.clone(),
+            "start" => format_timestamp(info.start, options),
+            "end" => format_timestamp(info.end, options),
+        },
+        "hosts" => hosts_value,
+        "categories" => categories,
+        "findings" => findings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{findings, fixtures};
+
+    fn render(src: &str, context: &Value) -> String {
+        Template::parse(src).unwrap().render(context)
+    }
+
+    fn parse_err(src: &str) -> String {
+        Template::parse(src).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn escapes_unless_raw() {
+        let context = map! { "name" => "<b>&</b>" };
+        assert_eq!(render("{{ name }}|{{{ name }}}|{{! dropped }}", &context), "&lt;b&gt;&amp;&lt;/b&gt;|<b>&</b>|");
+        assert_eq!(render("[{{missing}}]", &context), "[]");
+    }
+
+    #[test]
+    fn renders_nested_blocks() {
+        let context = map! {
+            "suffix" => "!",
+            "hosts" => vec![
+                map! { "addr" => "a", "ports" => vec![22u16, 2222] },
+                map! { "addr" => "b", "ports" => Vec::<u16>::new() },
+            ],
+        };
+        let src = "{{#each hosts}}{{@index}}:{{addr}}={{#if ports}}{{#each ports}}{{this}}{{suffix}}{{/each}}{{else}}none{{/if}};{{/each}}";
+        assert_eq!(render(src, &context), "0:a=22!2222!;1:b=none;");
+        let map = map! { "m" => map! { "x" => 1usize, "y" => 2usize } };
+        assert_eq!(render("{{#each m}}{{@key}}{{this}} {{/each}}", &map), "x1 y2 ");
+    }
+
+    #[test]
+    fn rejects_malformed_tags() {
+        assert!(parse_err("a\n{{name").contains("line 2: unterminated tag"));
+        assert!(parse_err("{{#if x}}{{/each}}").contains("mismatched closing tag"));
+        assert!(parse_err("{{#each x}}").contains("block is never closed"));
+        assert!(parse_err("{{/if}}").contains("closing tag without an open block"));
+        assert!(parse_err("{{else}}").contains("{{else}} outside of {{#if}}"));
+        assert!(parse_err("{{#eachfoo}}{{/each}}").contains("unknown block helper"));
+        assert!(parse_err("{{#unless x}}{{/unless}}").contains("unknown block helper"));
+        assert!(Template::parse("{{#each\tx}}{{/each}}").is_ok());
+    }
+
+    #[test]
+    fn renders_starter_template() {
+        let mut port = fixtures::port(2222, fixtures::algos(["diffie-hellman-group1-sha1,curve25519-sha256", "ssh-rsa", "3des-cbc,aes128-ctr", "hmac-md5", "none"]));
+        port.product = Some("Dropbear <sshd>".to_owned());
+        let hosts = vec![fixtures::host("2001:db8::1", vec![port])];
+        let findings = findings::collect(&hosts);
+        let agg_data = AggregatedData::build_from_hosts(&hosts);
+        let options = ReportOptions { footer: Some("Internal".to_owned()), ..ReportOptions::default() };
+        let context = build_context(&hosts, &agg_data, &ScanInfo::default(), &findings, &options);
+        let out = Template::parse(DEFAULT).unwrap().render(&context);
+        assert!(out.contains("<h3>[2001:db8::1]:2222 Dropbear &lt;sshd&gt;"), "{out}");
+        assert!(out.contains("id=\"host-2001-db8--1-2222\""));
+        assert!(out.contains("<a href=\"#algo-3des-cbc\">3des-cbc</a>"));
+        assert!(out.contains("Broken algorithm 3des-cbc offered"));
+        assert!(out.contains("<footer>Internal</footer>"));
+        assert!(!out.contains("{{"));
+    }
+}
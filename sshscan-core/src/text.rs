@@ -0,0 +1,140 @@
+use std::fmt::Write;
+use crate::{
+    agg_data::{self, AggregatedData, Summary},
+    findings::Finding,
+    html::HOST_HEADERS,
+    strength::{self, Grade, Strength},
+    host_port, Algos, Host,
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+
+/// Wraps text in ANSI escapes, or does nothing when color is disabled
+#[derive(Clone, Copy)]
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn paint(self, code: &str, text: &str) -> String {
+        if self.color && !code.is_empty() {
+            format!("{code}{text}{RESET}")
+        } else {
+            text.to_owned()
+        }
+    }
+
+    /// Pad before painting, escape codes would otherwise throw off the alignment
+    fn paint_padded(self, code: &str, text: &str, width: usize) -> String {
+        self.paint(code, &format!("{text:<width$}"))
+    }
+}
+
+const fn strength_code(strength: Strength) -> &'static str {
+    match strength {
+        Strength::Broken => RED,
+        Strength::Weak => YELLOW,
+        Strength::Strong => GREEN,
+        Strength::Unknown => "",
+    }
+}
+
+const fn grade_code(grade: Grade) -> &'static str {
+    match grade {
+        Grade::A | Grade::B => GREEN,
+        Grade::C => YELLOW,
+        Grade::D | Grade::F => RED,
+    }
+}
+
+/// Generate a compact plain text report for terminals: a per-host summary followed by
+/// an algorithm prevalence table. Weak and broken algorithms are highlighted when `color` is set.
+#[must_use]
+pub fn generate(hosts: &[Host], agg_data: &AggregatedData, key_changes: &[Finding], color: bool) -> String {
+    let painter = Painter { color };
+    let mut out = String::new();
+    let summary = Summary::build_from_hosts(hosts);
+    let _ = writeln!(
+        out,
+        "{} hosts up, {} down, {} open ports, {} closed/filtered\n",
+        summary.hosts_up, summary.hosts_down, summary.ports_open, summary.ports_closed,
+    );
+    if !key_changes.is_empty() {
+        let _ = writeln!(out, "{}", painter.paint(RED, &painter.paint(BOLD, "Host key changes")));
+        for change in key_changes {
+            let _ = writeln!(out, "  {}", painter.paint(RED, &format!("{}: {}", host_port(&change.addr, change.portid), change.message)));
+        }
+        out.push('\n');
+    }
+    write_hosts(&mut out, hosts, painter);
+    write_prevalence(&mut out, agg_data, painter);
+    out
+}
+
+fn weak_algos(algos: &Algos, painter: Painter) -> Vec<String> {
+    let mut res = Vec::new();
+    for category in Algos::KEYS {
+        for algo in &algos[category] {
+            let strength = strength::classify(category, algo);
+            if matches!(strength, Strength::Weak | Strength::Broken) {
+                res.push(painter.paint(strength_code(strength), algo));
+            }
+        }
+    }
+    res
+}
+
+fn write_hosts(out: &mut String, hosts: &[Host], painter: Painter) {
+    let id_width = hosts
+        .iter()
+        .flat_map(|h| h.port_states.iter().map(move |hp| host_port(&h.addr, hp.portid).len()))
+        .max()
+        .unwrap_or(0);
+    let _ = writeln!(out, "{}", painter.paint(BOLD, "Hosts"));
+    for (host, host_port) in hosts.iter().flat_map(|h| h.port_states.iter().map(move |hp| (h, hp))) {
+        let id = crate::host_port(&host.addr, host_port.portid);
+        let grade = match strength::grade(&host_port.algos) {
+            Some(grade) => painter.paint(grade_code(grade), grade.as_str()),
+            None => "-".to_owned(),
+        };
+        let state = if host_port.state { "open" } else { "closed" };
+        let _ = write!(out, "  {id:<id_width$}  {grade}  {state:<6}  {}", host_port.product.as_deref().unwrap_or("unknown"));
+        let weak = weak_algos(&host_port.algos, painter);
+        if !weak.is_empty() {
+            let _ = write!(out, "\n  {:<id_width$}     weak: {}", "", weak.join(", "));
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+}
+
+fn write_prevalence(out: &mut String, agg_data: &AggregatedData, painter: Painter) {
+    let rows: Vec<(&str, &str, Strength, usize, usize)> = HOST_HEADERS
+        .iter()
+        .flat_map(|header| {
+            agg_data::by_count(&agg_data[header.data_key]).into_iter().map(|(algo, offers)| {
+                (header.title, algo.as_str(), strength::classify(header.algo_key, algo), offers.len(), agg_data::first_choice_count(offers))
+            })
+        })
+        .collect();
+    let cat_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).max("Category".len());
+    let algo_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0).max("Algorithm".len());
+    let _ = writeln!(
+        out,
+        "{}",
+        painter.paint(BOLD, &format!("{:<cat_width$}  {:<algo_width$}  {:<8}  {:>5}  {:>5}", "Category", "Algorithm", "Rating", "Count", "First")),
+    );
+    for (category, algo, strength, count, first) in rows {
+        let code = strength_code(strength);
+        let _ = writeln!(
+            out,
+            "{category:<cat_width$}  {}  {}  {count:>5}  {first:>5}",
+            painter.paint_padded(code, algo, algo_width),
+            painter.paint_padded(code, strength.as_str(), 8),
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::{
+    agg_data::{AggregatedData, Summary},
+    findings::{self, FindingKind},
+    html::{HOST_HEADERS, ReportOptions},
+    strength::{self, Grade, Strength},
+    host_port, Host, ScanInfo,
+};
+
+/// One scan in a trend, in chronological order
+#[derive(Debug, Clone)]
+pub struct TrendScan {
+    /// Unix timestamp of the scan start (or end), `None` when the scan data has no times
+    pub time: Option<i64>,
+    /// Host:ports with an open port
+    pub ports_open: usize,
+    /// Host:ports per grade, indexed like [`Grade::ALL`]
+    pub grades: [usize; 5],
+}
+
+/// Number of host:ports offering an algorithm in each scan
+#[derive(Debug, Clone)]
+pub struct Prevalence {
+    pub category: &'static str,
+    pub algorithm: String,
+    pub strength: Strength,
+    pub counts: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GradeChange {
+    pub host_port: String,
+    pub before: Grade,
+    pub after: Grade,
+}
+
+/// Changes between a scan and the one before it
+#[derive(Debug, Clone, Default)]
+pub struct Transition {
+    /// Index of the later scan
+    pub scan: usize,
+    pub fixed: Vec<GradeChange>,
+    pub regressed: Vec<GradeChange>,
+    /// Host:ports open now but not in the previous scan
+    pub appeared: Vec<String>,
+    /// Host:ports open in the previous scan but not anymore
+    pub disappeared: Vec<String>,
+}
+
+impl Transition {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.regressed.is_empty() && self.appeared.is_empty() && self.disappeared.is_empty()
+    }
+}
+
+/// A policy violation from first sighting until it was fixed, if it was
+#[derive(Debug, Clone)]
+pub struct Remediation {
+    pub host_port: String,
+    pub kind: FindingKind,
+    pub subject: String,
+    /// Scan index the finding was first seen in
+    pub first_seen: usize,
+    /// Scan index the host:port was first seen again without the finding
+    pub fixed: Option<usize>,
+}
+
+impl Remediation {
+    /// Seconds between first sighting and fix, when both scans have times
+    #[must_use]
+    pub fn duration(&self, scans: &[TrendScan]) -> Option<i64> {
+        Some(scans[self.fixed?].time? - scans[self.first_seen].time?)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trend {
+    pub scans: Vec<TrendScan>,
+    pub prevalence: Vec<Prevalence>,
+    pub transitions: Vec<Transition>,
+    pub remediations: Vec<Remediation>,
+}
+
+fn port_grades(hosts: &[Host]) -> BTreeMap<String, Option<Grade>> {
+    hosts
+        .iter()
+        .flat_map(|h| h.port_states.iter().filter(|hp| hp.state).map(move |hp| (host_port(&h.addr, hp.portid), strength::grade(&hp.algos))))
+        .collect()
+}
+
+impl Trend {
+    /// Compare scans, which are sorted by time first. Scans without times keep their order at the end.
+    #[must_use]
+    pub fn build(scans: &[(ScanInfo, Vec<Host>)]) -> Self {
+        let mut order: Vec<&(ScanInfo, Vec<Host>)> = scans.iter().collect();
+        order.sort_by_key(|(info, _)| (info.start.or(info.end).is_none(), info.start.or(info.end)));
+        let mut res = Self::default();
+        let mut prevalence: BTreeMap<(usize, String), Vec<usize>> = BTreeMap::new();
+        let mut previous: Option<BTreeMap<String, Option<Grade>>> = None;
+        // Open remediation per (host:port, kind, subject)
+        let mut open: HashMap<(String, FindingKind, String), usize> = HashMap::new();
+        for (idx, (info, hosts)) in order.iter().enumerate() {
+            let summary = Summary::build_from_hosts(hosts);
+            res.scans.push(TrendScan { time: info.start.or(info.end), ports_open: summary.ports_open, grades: summary.grades });
+
+            let agg_data = AggregatedData::build_from_hosts(hosts);
+            for (cat_idx, header) in HOST_HEADERS.iter().enumerate() {
+                for (algo, offers) in &agg_data[header.data_key] {
+                    prevalence.entry((cat_idx, algo.clone())).or_insert_with(|| vec![0; order.len()])[idx] = offers.len();
+                }
+            }
+
+            let grades = port_grades(hosts);
+            if let Some(before) = &previous {
+                let mut transition = Transition { scan: idx, ..Transition::default() };
+                for (host_port, after) in &grades {
+                    match (before.get(host_port), after) {
+                        (None, _) => transition.appeared.push(host_port.clone()),
+                        (Some(Some(b)), Some(a)) if a < b => {
+                            transition.fixed.push(GradeChange { host_port: host_port.clone(), before: *b, after: *a });
+                        }
+                        (Some(Some(b)), Some(a)) if a > b => {
+                            transition.regressed.push(GradeChange { host_port: host_port.clone(), before: *b, after: *a });
+                        }
+                        _ => {}
+                    }
+                }
+                transition.disappeared = before.keys().filter(|hp| !grades.contains_key(*hp)).cloned().collect();
+                res.transitions.push(transition);
+            }
+
+            let mut current: HashSet<(String, FindingKind, String)> = HashSet::new();
+            for finding in findings::collect(hosts).into_iter().filter(findings::Finding::is_violation) {
+                let key = (host_port(&finding.addr, finding.portid), finding.kind, finding.subject);
+                open.entry(key.clone()).or_insert(idx);
+                current.insert(key);
+            }
+            // A finding is fixed once its host:port shows up again without it
+            let fixed: Vec<_> = open.keys().filter(|key| grades.contains_key(&key.0) && !current.contains(*key)).cloned().collect();
+            for key in fixed {
+                if let Some(first_seen) = open.remove(&key) {
+                    res.remediations.push(Remediation { host_port: key.0, kind: key.1, subject: key.2, first_seen, fixed: Some(idx) });
+                }
+            }
+            previous = Some(grades);
+        }
+        res.remediations.extend(
+            open.into_iter().map(|(key, first_seen)| Remediation { host_port: key.0, kind: key.1, subject: key.2, first_seen, fixed: None }),
+        );
+        res.remediations.sort_by(|a, b| (a.first_seen, &a.host_port, a.kind, &a.subject).cmp(&(b.first_seen, &b.host_port, b.kind, &b.subject)));
+        let mut prevalence: Vec<(usize, Prevalence)> = prevalence
+            .into_iter()
+            .map(|((cat_idx, algorithm), counts)| {
+                let header = &HOST_HEADERS[cat_idx];
+                (cat_idx, Prevalence { category: header.title, strength: strength::classify(header.algo_key, &algorithm), algorithm, counts })
+            })
+            .collect();
+        // Most offered in the latest scan first within each category
+        prevalence.sort_by(|(ca, a), (cb, b)| ca.cmp(cb).then_with(|| b.counts.last().cmp(&a.counts.last())).then_with(|| a.algorithm.cmp(&b.algorithm)));
+        res.prevalence = prevalence.into_iter().map(|(_, p)| p).collect();
+        res
+    }
+
+    /// Display label of a scan: its formatted time, or its position when it has none
+    #[must_use]
+    pub fn scan_label(&self, idx: usize, options: &ReportOptions) -> String {
+        self.scans[idx].time.and_then(|t| options.format_timestamp(t)).unwrap_or_else(|| format!("Scan {}", idx + 1))
+    }
+
+    /// Mean seconds to remediate per finding kind, over fixed findings with known times
+    #[must_use]
+    pub fn mean_time_to_remediate(&self) -> Vec<(FindingKind, i64, usize)> {
+        FindingKind::ALL
+            .iter()
+            .filter_map(|kind| {
+                let durations: Vec<i64> = self.remediations.iter().filter(|r| r.kind == *kind).filter_map(|r| r.duration(&self.scans)).collect();
+                let count = durations.len();
+                (count > 0).then(|| (*kind, durations.iter().sum::<i64>() / i64::try_from(count).unwrap_or(1), count))
+            })
+            .collect()
+    }
+}
+
+/// Format a duration in seconds as hours below two days, days otherwise
+#[must_use]
+pub fn format_duration(seconds: i64) -> String {
+    const HOUR: i64 = 3600;
+    const DAY: i64 = 24 * HOUR;
+    if seconds < 2 * DAY {
+        format!("{}h", seconds / HOUR)
+    } else {
+        format!("{}d", seconds / DAY)
+    }
+}
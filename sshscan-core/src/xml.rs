@@ -1,4 +1,4 @@
-use crate::{Host, SshScanErr, HostStatus, Description, Algos};
+use crate::{Host, SshScanErr, HostStatus, Description, Algos, ScanInfo, HostKey};
 use xmltree::{Element, XMLNode};
 
 /// Parse XML file from nmap ssh2-enum-algos.
@@ -6,19 +6,43 @@ use xmltree::{Element, XMLNode};
 /// Outer Result turns error on error from Read or if the XML is so badly formed such that the root element is not parsable
 /// Inner Result returns error if a specific host failed to parse due to XML valiadtion failure
 pub fn process_xml<R>(xml: R, filter_down: bool) -> Result<Vec<Result<Host, SshScanErr>>, SshScanErr>
+where R: std::io::Read {
+    process_xml_with_info(xml, filter_down).map(|(_, hosts)| hosts)
+}
+
+/// Like [`process_xml`], but also returns the metadata of the nmap run.
+/// # Errors
+/// Same as [`process_xml`]
+pub fn process_xml_with_info<R>(xml: R, filter_down: bool) -> Result<(ScanInfo, Vec<Result<Host, SshScanErr>>), SshScanErr>
 where R: std::io::Read {
     let mut res = Vec::new();
     let root = Element::parse(xml)?;
+    let mut info = ScanInfo {
+        args: root.attributes.get("args").cloned(),
+        nmap_version: root.attributes.get("version").cloned(),
+        start: root.attributes.get("start").and_then(|s| s.parse().ok()),
+        end: None,
+    };
     for e in &root.children {
-        if let Some(elem) = e.as_element() && elem.name == "host" {
-            res.push(process_host(elem, filter_down));
+        if let Some(elem) = e.as_element() {
+            match elem.name.as_str() {
+                "host" => res.push(process_host(elem, filter_down)),
+                "runstats" => {
+                    info.end = elem
+                        .get_child("finished")
+                        .and_then(|finished| finished.attributes.get("time"))
+                        .and_then(|s| s.parse().ok());
+                },
+                _ => {}
+            }
         }
     }
-    Ok(res)
+    Ok((info, res))
 }
 
 fn process_host(host_elem: &Element, filter_down: bool) -> Result<Host, SshScanErr> {
     let mut host_addr = String::new();
+    let mut hostnames = Vec::new();
     let mut host_status: HostStatus = HostStatus::Unknown;
     let mut descrs: Vec<Description> = Vec::new();
     for child in &host_elem.children {
@@ -37,6 +61,15 @@ fn process_host(host_elem: &Element, filter_down: bool) -> Result<Host, SshScanE
                         host_addr.clone_from(addr);
                     }
                 },
+                "hostnames" => {
+                    for hostname in child.children.iter().filter_map(XMLNode::as_element).filter(|e| e.name == "hostname") {
+                        if let Some(name) = hostname.attributes.get("name")
+                            && !hostnames.contains(name)
+                        {
+                            hostnames.push(name.clone());
+                        }
+                    }
+                },
                 "ports" => {
                     for port_elem in &child.children {
                         let port = process_host_port(port_elem)?;
@@ -53,6 +86,7 @@ fn process_host(host_elem: &Element, filter_down: bool) -> Result<Host, SshScanE
     Ok(Host {
         status: host_status,
         addr: host_addr,
+        hostnames,
         port_states: descrs,
     })
 }
@@ -62,6 +96,8 @@ fn process_host_port(port_elem: &XMLNode) -> Result<Description, SshScanErr> {
     let mut port = 0u16;
     let mut algos = Algos::default();
     let mut product: Option<String> = None;
+    let mut sshv1 = false;
+    let mut host_keys = Vec::new();
     if let Some(port_elem) = port_elem.as_element() {
         port = port_elem.attributes.get("portid").map_or(0, |pid_s| pid_s.parse().unwrap_or(0));
         for child in &port_elem.children {
@@ -71,6 +107,12 @@ fn process_host_port(port_elem: &XMLNode) -> Result<Description, SshScanErr> {
                         state = child.attributes.get("state").is_some_and(|maybe_open|maybe_open.eq_ignore_ascii_case("open"));
                     }
                     "script" => {
+                        // The sshv1 script only produces output when the server accepts protocol 1
+                        match child.attributes.get("id").map(String::as_str) {
+                            Some("sshv1") => sshv1 = true,
+                            Some("ssh-hostkey") => host_keys = process_hostkey_script(child),
+                            _ => {}
+                        }
                         process_script(child, &mut algos)?;
                     }
                     "service" => {
@@ -84,6 +126,8 @@ fn process_host_port(port_elem: &XMLNode) -> Result<Description, SshScanErr> {
                             temp += " ";
                         }
                         if let Some(xtra) = child.attributes.get("extrainfo") {
+                            // e.g. "protocol 1.99" for servers speaking both versions
+                            sshv1 |= xtra.contains("protocol 1.");
                             temp += xtra;
                             temp += " ";
                         }
@@ -97,14 +141,40 @@ fn process_host_port(port_elem: &XMLNode) -> Result<Description, SshScanErr> {
             }
         }
     }
+    let hassh = (!algos.is_empty()).then(|| algos.hassh_server());
     Ok(Description {
         portid: port,
         state,
         algos,
         product,
+        hassh,
+        sshv1,
+        host_keys,
     })
 }
 
+/// Read the key tables of an ssh-hostkey script, skipping any that are incomplete
+fn process_hostkey_script(script_elem: &Element) -> Vec<HostKey> {
+    let mut res = Vec::new();
+    for table_elem in script_elem.children.iter().filter_map(XMLNode::as_element).filter(|e| e.name == "table") {
+        let mut key = HostKey::default();
+        for elem in table_elem.children.iter().filter_map(XMLNode::as_element) {
+            let text = elem.get_text().map(|t| t.trim().to_owned()).unwrap_or_default();
+            match elem.attributes.get("key").map(String::as_str) {
+                Some("type") => key.key_type = text,
+                Some("bits") => key.bits = text.parse().ok(),
+                Some("fingerprint") => key.fingerprint = text.to_ascii_lowercase(),
+                Some("key") => key.key = Some(text),
+                _ => {}
+            }
+        }
+        if !key.key_type.is_empty() && !key.fingerprint.is_empty() {
+            res.push(key);
+        }
+    }
+    res
+}
+
 fn process_script(script_elem: &Element, algos: &mut Algos) -> Result<(), SshScanErr> {
     match script_elem.attributes.get("id") {
         None => { return Ok(()) }